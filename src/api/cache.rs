@@ -0,0 +1,125 @@
+/*
+ * Created on Thu May 27 2021
+ *
+ * Copyright (c) storycraft. Licensed under the GNU General Public License v3.
+ */
+
+//! On-disk api response cache
+
+use std::{
+    io,
+    path::PathBuf,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+use sha1::{Digest, Sha1};
+use tokio::fs;
+
+/// Cached api response
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheEntry {
+    /// Request url
+    pub url: String,
+
+    /// Fetched time (unix second)
+    pub fetched: u64,
+
+    /// Response body
+    pub body: String,
+}
+
+impl CacheEntry {
+    /// Time elapsed since fetched
+    pub fn age(&self) -> Duration {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+
+        now.saturating_sub(Duration::from_secs(self.fetched))
+    }
+}
+
+/// Api response cache keyed by request url
+#[derive(Debug, Clone)]
+pub struct ApiCache {
+    /// Cache directory
+    dir: PathBuf,
+
+    /// Time to live of cached responses
+    ttl: Duration,
+
+    /// Serve only from cache
+    offline: bool,
+}
+
+impl ApiCache {
+    pub fn new(dir: PathBuf, ttl: Duration, offline: bool) -> Self {
+        Self { dir, ttl, offline }
+    }
+
+    /// Cache directory
+    pub fn dir(&self) -> &PathBuf {
+        &self.dir
+    }
+
+    /// Time to live of cached responses
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// true if responses should be served only from cache
+    pub fn offline(&self) -> bool {
+        self.offline
+    }
+
+    fn entry_path(&self, url: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", hex::encode(Sha1::digest(url.as_bytes()))))
+    }
+
+    /// Load cached response of url. Returns None if not cached or unreadable.
+    pub async fn load(&self, url: &str) -> Option<CacheEntry> {
+        let data = fs::read(self.entry_path(url)).await.ok()?;
+        let entry = serde_json::from_slice::<CacheEntry>(&data).ok()?;
+
+        // Ignore hash collision
+        if entry.url == url {
+            Some(entry)
+        } else {
+            None
+        }
+    }
+
+    /// Store response body of url
+    pub async fn store(&self, url: &str, body: &[u8]) -> Result<(), io::Error> {
+        let entry = CacheEntry {
+            url: url.into(),
+            fetched: SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs(),
+            body: String::from_utf8_lossy(body).into_owned(),
+        };
+
+        fs::create_dir_all(&self.dir).await?;
+        fs::write(self.entry_path(url), serde_json::to_vec(&entry)?).await
+    }
+
+    fn resource_path(&self, url: &str) -> PathBuf {
+        self.dir.join(format!("{}.bin", hex::encode(Sha1::digest(url.as_bytes()))))
+    }
+
+    /// Load cached binary resource of url. Returns None if not cached or unreadable.
+    pub async fn load_resource(&self, url: &str) -> Option<Vec<u8>> {
+        fs::read(self.resource_path(url)).await.ok()
+    }
+
+    /// Store binary resource of url. Resources are stored as is and never expire.
+    pub async fn store_resource(&self, url: &str, body: &[u8]) -> Result<(), io::Error> {
+        fs::create_dir_all(&self.dir).await?;
+        fs::write(self.resource_path(url), body).await
+    }
+
+    /// Remove cached response of url
+    pub async fn remove(&self, url: &str) -> Result<(), io::Error> {
+        match fs::remove_file(self.entry_path(url)).await {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+            _ => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,114 @@
+/*
+ * Created on Wed May 19 2021
+ *
+ * Copyright (c) storycraft. Licensed under the GNU General Public License v3.
+ */
+
+//! This module includes curseforge addon file structs.
+
+use serde::{Deserialize, Serialize};
+
+use crate::api::modpack::data::{FileVersion, PackFile, PackFileInfo, PackFileType};
+
+/// Hash algorithm id of sha1 in curseforge file hashes
+pub const SHA1_ALGO: u32 = 1;
+
+/// Curseforge addon file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AddonFile {
+    /// File id
+    pub id: u32,
+
+    /// Display name
+    pub display_name: String,
+
+    /// File name with extension
+    pub file_name: String,
+
+    /// File upload date with ISO format
+    pub file_date: String,
+
+    /// File size (byte)
+    pub file_length: i64,
+
+    /// File url. None if the author disallowed third party downloads.
+    #[serde(default)]
+    pub download_url: Option<String>,
+
+    /// File hashes
+    #[serde(default)]
+    pub hashes: Vec<AddonFileHash>,
+
+    /// Top level entries of the file
+    #[serde(default)]
+    pub modules: Vec<AddonFileModule>,
+}
+
+impl AddonFile {
+
+    /// Sha1 hex hash of the file if presents
+    pub fn sha1(&self) -> Option<&str> {
+        self.hashes
+            .iter()
+            .find(|hash| hash.algo == SHA1_ALGO)
+            .map(|hash| hash.value.as_str())
+    }
+
+    /// true if the file is resource pack
+    pub fn is_resource_pack(&self) -> bool {
+        self.modules
+            .iter()
+            .any(|module| module.name == "pack.mcmeta")
+    }
+
+    /// Convert to modpack file so it can be installed like other pack files.
+    /// Returns None if the file cannot be downloaded.
+    pub fn to_pack_file(&self, optional: bool) -> Option<PackFile> {
+        let url = self.download_url.clone()?;
+
+        let (file_type, path) = if self.is_resource_pack() {
+            (PackFileType::Resource, "resourcepacks")
+        } else {
+            (PackFileType::Mod, "mods")
+        };
+
+        Some(PackFile {
+            file_type,
+            info: PackFileInfo {
+                id: self.id,
+                name: self.file_name.clone(),
+                optional,
+                path: path.into(),
+                clientonly: false,
+                serveronly: false,
+                sha1: self.sha1().unwrap_or_default().into(),
+                size: Some(self.file_length as u64).filter(|_| self.file_length > 0),
+                updated: 0,
+                url,
+                version: FileVersion::Semantic(self.display_name.clone()),
+            },
+        })
+    }
+
+}
+
+/// Curseforge file hash
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AddonFileHash {
+    /// Hash hex
+    pub value: String,
+
+    /// Hash algorithm id (1 = sha1, 2 = md5)
+    pub algo: u32,
+}
+
+/// Curseforge file module
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AddonFileModule {
+    /// Top level entry name
+    pub name: String,
+
+    /// Entry fingerprint
+    pub fingerprint: i64,
+}
@@ -0,0 +1,107 @@
+/*
+ * Created on Wed May 19 2021
+ *
+ * Copyright (c) storycraft. Licensed under the GNU General Public License v3.
+ */
+
+//! Curseforge addon API type declarations
+
+pub mod file;
+
+use reqwest::Client;
+use serde::{de::DeserializeOwned, Deserialize};
+
+use crate::api::{
+    cache::ApiCache,
+    curseforge::file::AddonFile,
+    modpack::{parse_json, read_body, APIResult, ModPackApiError},
+};
+
+/// Curseforge api endpoint
+pub const CURSEFORGE_API_URL: &str = "https://api.curseforge.com";
+
+/// Curseforge api key header
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// Curseforge api response wrapper
+#[derive(Debug, Deserialize)]
+struct DataResponse<T> {
+    data: T,
+}
+
+/// Curseforge api client
+#[derive(Debug, Clone)]
+pub struct CurseForgeAPI {
+    /// Shared http client
+    client: Client,
+
+    /// Api base url
+    base_url: String,
+
+    /// Api key sent with every request. Curseforge rejects requests without it.
+    api_key: Option<String>,
+
+    /// Response cache
+    cache: Option<ApiCache>,
+}
+
+impl CurseForgeAPI {
+
+    /// Create api client with custom client, base url and api key
+    pub fn new_client(client: Client, base_url: String, api_key: Option<String>) -> Self {
+        Self::new_cached(client, base_url, api_key, None)
+    }
+
+    /// Create api client with custom client, base url, api key and response cache
+    pub fn new_cached(client: Client, base_url: String, api_key: Option<String>, cache: Option<ApiCache>) -> Self {
+        Self { client, base_url, api_key, cache }
+    }
+
+    /// true if api key is set
+    pub fn has_api_key(&self) -> bool {
+        self.api_key.is_some()
+    }
+
+    /// Returns api endpoint
+    pub fn endpoint(&self, path: &str) -> String {
+        format!("{}/{}", self.base_url.trim_end_matches('/'), path)
+    }
+
+    /// Get response data using cache. Requested resources never change so cached response never expires.
+    async fn get<T: DeserializeOwned>(&self, path: &str) -> APIResult<T> {
+        let url = self.endpoint(path);
+
+        if let Some(cache) = &self.cache {
+            if let Some(entry) = cache.load(&url).await {
+                if let Ok(res) = parse_json::<DataResponse<T>>(url.clone(), entry.body.as_bytes()) {
+                    return Ok(res.data);
+                }
+            }
+
+            if cache.offline() {
+                return Err(ModPackApiError::NotCached(url));
+            }
+        }
+
+        let mut req = self.client.get(&url);
+        if let Some(api_key) = &self.api_key {
+            req = req.header(API_KEY_HEADER, api_key);
+        }
+
+        let body = read_body(req.send().await?).await?;
+        let res = parse_json::<DataResponse<T>>(url.clone(), &body)?;
+
+        if let Some(cache) = &self.cache {
+            // Cache is best effort
+            cache.store(&url, &body).await.ok();
+        }
+
+        Ok(res.data)
+    }
+
+    /// Get addon file information using project id and file id
+    pub async fn addon_file(&self, project_id: u32, file_id: u32) -> APIResult<AddonFile> {
+        self.get(&format!("v1/mods/{}/files/{}", project_id, file_id)).await
+    }
+
+}
@@ -4,5 +4,7 @@
  * Copyright (c) storycraft. Licensed under the GNU General Public License v3.
  */
 
+pub mod cache;
+pub mod curseforge;
 pub mod modpack;
 pub mod overrides;
@@ -6,7 +6,11 @@
 
 //! This module includes modpack data structs.
 
-use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::util::path::join_install_path;
 
 use super::{PackLink, PackSpec};
 
@@ -113,11 +117,13 @@ pub struct PackFileInfo {
     /// true if only server side file
     pub serveronly: bool,
 
-    /// File sha1 hash (hex)
+    /// File sha1 hash (hex). Empty if unknown.
+    #[serde(default)]
     pub sha1: String,
 
-    /// File size (byte)
-    pub size: i64,
+    /// File size (byte). None if unknown.
+    #[serde(default, deserialize_with = "unknown_size_as_none")]
+    pub size: Option<u64>,
 
     // pub tags: Vec<PackTag>,
     /// File update time
@@ -130,6 +136,24 @@ pub struct PackFileInfo {
     pub version: FileVersion,
 }
 
+impl PackFileInfo {
+
+    /// Relative install path of the file including file name.
+    /// Returns None if path is absolute, escapes install location or name is not a plain file name.
+    pub fn install_path(&self) -> Option<PathBuf> {
+        join_install_path(&self.path, &self.name)
+    }
+
+}
+
+/// Deserialize zero or negative file size as unknown
+pub fn unknown_size_as_none<'de, D>(de: D) -> Result<Option<u64>, D::Error>
+where
+    D: Deserializer<'de>
+{
+    Ok(Option::<i64>::deserialize(de)?.filter(|size| *size > 0).map(|size| size as u64))
+}
+
 /// File version
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(untagged)]
@@ -159,3 +183,73 @@ pub struct PackTarget {
     /// Target version
     pub version: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::{FileVersion, PackFileInfo};
+
+    fn file_info(path: &str, name: &str) -> PackFileInfo {
+        PackFileInfo {
+            id: 0,
+            name: name.into(),
+            optional: false,
+            path: path.into(),
+            clientonly: false,
+            serveronly: false,
+            sha1: String::new(),
+            size: None,
+            updated: 0,
+            url: String::new(),
+            version: FileVersion::Numberic(0),
+        }
+    }
+
+    #[test]
+    fn install_path_under_root() {
+        let root = Path::new("/tmp/pack");
+
+        let cases = [
+            ("./mods/", "a.jar", Path::new("mods").join("a.jar")),
+            ("mods", "a.jar", Path::new("mods").join("a.jar")),
+            ("", "a.jar", Path::new("a.jar").to_path_buf()),
+            ("config//sub/", "a.cfg", Path::new("config").join("sub").join("a.cfg")),
+            ("config\\sub", "a.cfg", Path::new("config").join("sub").join("a.cfg")),
+        ];
+
+        for (path, name, expected) in &cases {
+            let install_path = file_info(path, name).install_path().unwrap();
+
+            assert_eq!(&install_path, expected);
+            assert!(root.join(&install_path).starts_with(root));
+        }
+    }
+
+    #[test]
+    fn reject_unsafe_install_path() {
+        let cases = [
+            ("..", "a.jar"),
+            ("mods/../..", "a.jar"),
+            ("mods\\..\\..", "a.jar"),
+            ("/etc", "a.jar"),
+            ("\\Windows", "a.dll"),
+            ("C:\\Windows", "a.dll"),
+            ("C:", "a.dll"),
+            ("\\\\server\\share", "a.dll"),
+            ("mods", ".."),
+            ("mods", "../a.jar"),
+            ("mods", "..\\a.jar"),
+            ("mods", "sub/a.jar"),
+            ("mods", "sub\\a.jar"),
+            ("mods", "/a.jar"),
+            ("mods", "C:a.jar"),
+            ("mods", ""),
+            ("mods", "."),
+        ];
+
+        for (path, name) in &cases {
+            assert_eq!(file_info(path, name).install_path(), None, "{}/{} was accepted", path, name);
+        }
+    }
+}
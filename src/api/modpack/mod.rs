@@ -9,12 +9,21 @@
 pub mod info;
 pub mod data;
 pub mod search;
-pub mod download_stream;
 
-use reqwest::get;
-use serde::{Deserialize, Serialize};
+use std::{
+    error::Error,
+    fmt::{self, Display, Formatter},
+    time::Duration,
+};
 
-use crate::api::modpack::{data::PackVersionData, info::ModPack, search::SearchResult};
+use bytes::Bytes;
+use reqwest::{header::HeaderMap, Client, Response, StatusCode};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::api::{
+    cache::ApiCache,
+    modpack::{data::PackVersionData, info::ModPack, search::SearchResult},
+};
 
 /// Modpack spec requirement
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -52,11 +61,12 @@ pub fn empty_str_spec_as_none<'de, D>(de: D) -> Result<Option<PackSpec>, D::Erro
 where
     D: serde::Deserializer<'de>
 {
-    let res = ResSpecOption::deserialize(de)?;
+    // Serialized None is null
+    let res = Option::<ResSpecOption>::deserialize(de)?;
 
     match res {
-        ResSpecOption::Some(spec) => Ok(Some(spec)),
-        ResSpecOption::NoneEmptyStr(_) => Ok(None)
+        Some(ResSpecOption::Some(spec)) => Ok(Some(spec)),
+        Some(ResSpecOption::NoneEmptyStr(_)) | None => Ok(None)
     }
 }
 
@@ -88,53 +98,288 @@ pub struct PackTag {
 }
 
 /// modpack.ch api endpoint
-pub const API_URL: &'static str = "https://api.modpacks.ch";
+pub const API_URL: &str = "https://api.modpacks.ch";
+
+/// Api request errors
+#[derive(Debug)]
+pub enum ModPackApiError {
+    /// Connection or protocol error
+    Network(reqwest::Error),
+
+    /// Server responded with error status. (status, url)
+    Status(StatusCode, String),
+
+    /// Api responded with error payload. (status, message)
+    Api(String, String),
+
+    /// Response doesn't match expected schema. (url, json path, error)
+    Deserialize(String, String, serde_json::Error),
+
+    /// Response is not cached in offline mode. (url)
+    NotCached(String),
+}
+
+impl From<reqwest::Error> for ModPackApiError {
+    fn from(err: reqwest::Error) -> Self {
+        ModPackApiError::Network(err)
+    }
+}
+
+impl Display for ModPackApiError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match &self {
+            ModPackApiError::Network(err) => write!(f, "Network error. err: {}", err),
+            ModPackApiError::Status(status, url) => write!(f, "Server responded {} for {}", status, url),
+            ModPackApiError::Api(status, message) => write!(f, "Api responded {}. {}", status, message),
+            ModPackApiError::Deserialize(url, path, err) => {
+                write!(f, "Unexpected response from {} at {}. err: {}", url, path, err)
+            }
+            ModPackApiError::NotCached(url) => write!(f, "{} is not cached. Cannot request in offline mode", url),
+        }
+    }
+}
 
-/// Returns api endpoint
-#[inline]
-pub fn api_endpoint(path: &str) -> String {
-    format!("{}/{}", API_URL, path)
+impl Error for ModPackApiError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self {
+            ModPackApiError::Network(err) => Some(err),
+            ModPackApiError::Deserialize(_, _, err) => Some(err),
+            ModPackApiError::Status(_, _) | ModPackApiError::Api(_, _) | ModPackApiError::NotCached(_) => None,
+        }
+    }
 }
 
-pub type APIResult<T> = Result<T, reqwest::Error>;
+pub type APIResult<T> = Result<T, ModPackApiError>;
 
-pub struct ModPackAPI;
+/// Api status payload
+#[derive(Debug, Deserialize)]
+struct ApiStatus {
+    status: String,
+
+    #[serde(default)]
+    message: String,
+}
+
+/// Version data update time
+#[derive(Debug, Deserialize)]
+struct VersionStamp {
+    updated: u32,
+}
+
+/// Read json response. Api error payload and error status are returned as error.
+pub async fn read_json<T: DeserializeOwned>(res: Response) -> APIResult<T> {
+    let url = res.url().to_string();
+    let body = read_body(res).await?;
+
+    parse_json(url, &body)
+}
+
+/// Read response body. Api error payload and error status are returned as error.
+pub async fn read_body(res: Response) -> APIResult<Bytes> {
+    let status = res.status();
+    let url = res.url().to_string();
+    let body = res.bytes().await?;
+
+    if let Ok(payload) = serde_json::from_slice::<ApiStatus>(&body) {
+        if payload.status == "error" {
+            return Err(ModPackApiError::Api(payload.status, payload.message));
+        }
+    }
+
+    if !status.is_success() {
+        return Err(ModPackApiError::Status(status, url));
+    }
+
+    Ok(body)
+}
+
+/// Parse json response body of url
+pub fn parse_json<T: DeserializeOwned>(url: String, body: &[u8]) -> APIResult<T> {
+    let de = &mut serde_json::Deserializer::from_slice(body);
+
+    serde_path_to_error::deserialize(de)
+        .map_err(|err| ModPackApiError::Deserialize(url, err.path().to_string(), err.into_inner()))
+}
+
+/// modpacks.ch api client
+#[derive(Debug, Clone)]
+pub struct ModPackAPI {
+    /// Shared http client
+    client: Client,
+
+    /// Api base url
+    base_url: String,
+
+    /// Headers added to every api request
+    headers: HeaderMap,
+
+    /// Response cache
+    cache: Option<ApiCache>,
+}
 
 impl ModPackAPI {
 
-    /// Search modpacks with limit
-    pub async fn search(term: &str, limit: u32) -> APIResult<SearchResult> {
-        let res = get(api_endpoint(&format!("public/modpack/search/{}?term={}", limit, term))).await?;
+    /// Create api client using default client and base url
+    pub fn new() -> Self {
+        Self::new_client(Client::new(), API_URL.into())
+    }
 
-        res.json::<SearchResult>().await
+    /// Create api client with custom client and base url
+    pub fn new_client(client: Client, base_url: String) -> Self {
+        Self::new_headers(client, base_url, HeaderMap::new())
     }
 
-    /// Get modpack manifest using pack id
-    pub async fn modpack_manifest(pack_id: u32) -> APIResult<ModPack> {
-        let res = get(api_endpoint(&format!("public/modpack/{}", pack_id))).await?;
+    /// Create api client with custom client, base url and headers added to every api request
+    pub fn new_headers(client: Client, base_url: String, headers: HeaderMap) -> Self {
+        Self::new_cached(client, base_url, headers, None)
+    }
 
-        res.json::<ModPack>().await
+    /// Create api client with custom client, base url, headers and response cache
+    pub fn new_cached(client: Client, base_url: String, headers: HeaderMap, cache: Option<ApiCache>) -> Self {
+        Self { client, base_url, headers, cache }
     }
 
-    /// Get modpack version data using pack id and version id
-    pub async fn modpack_version_data(pack_id: u32, version_id: u32) -> APIResult<PackVersionData> {
-        let res = get(api_endpoint(&format!("public/modpack/{}/{}", pack_id, version_id))).await?;
+    /// Shared http client
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    /// Api base url
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// Returns api endpoint
+    pub fn endpoint(&self, path: &str) -> String {
+        format!("{}/{}", self.base_url.trim_end_matches('/'), path)
+    }
+
+    /// Response cache
+    pub fn cache(&self) -> Option<&ApiCache> {
+        self.cache.as_ref()
+    }
+
+    /// Get response using cache. Cached response older than max_age is requested again.
+    /// Returns true with response if it was requested.
+    async fn get<T: DeserializeOwned>(&self, path: &str, max_age: Option<Duration>) -> APIResult<(T, bool)> {
+        let url = self.endpoint(path);
+
+        if let Some(cache) = &self.cache {
+            if let Some(entry) = cache.load(&url).await {
+                if cache.offline() || max_age.is_none_or(|max_age| entry.age() < max_age) {
+                    // Request again if the response is outdated format
+                    if let Ok(value) = parse_json(url.clone(), entry.body.as_bytes()) {
+                        return Ok((value, false));
+                    }
+                }
+            }
+
+            if cache.offline() {
+                return Err(ModPackApiError::NotCached(url));
+            }
+        }
+
+        let res = self.client.get(&url).headers(self.headers.clone()).send().await?;
+        let body = read_body(res).await?;
+        let value = parse_json(url.clone(), &body)?;
+
+        if let Some(cache) = &self.cache {
+            // Cache is best effort
+            cache.store(&url, &body).await.ok();
+        }
+
+        Ok((value, true))
+    }
+
+    /// Get manifest and remove cached version data updated before version in new manifest
+    async fn get_manifest(&self, path: &str) -> APIResult<ModPack> {
+        let cache = match &self.cache {
+            Some(cache) => cache,
+            None => return Ok(self.get(path, None).await?.0),
+        };
+
+        let (pack, requested) = self.get::<ModPack>(path, Some(cache.ttl())).await?;
+
+        if requested {
+            for version in &pack.versions {
+                let url = self.endpoint(&format!("{}/{}", path, version.id));
+
+                if let Some(entry) = cache.load(&url).await {
+                    let outdated = serde_json::from_str::<VersionStamp>(&entry.body)
+                        .map_or(true, |stamp| stamp.updated < version.updated);
+
+                    if outdated {
+                        cache.remove(&url).await.ok();
+                    }
+                }
+            }
+        }
+
+        Ok(pack)
+    }
+
+    /// Search modpacks with limit
+    pub async fn search(&self, term: &str, limit: u32) -> APIResult<SearchResult> {
+        let ttl = self.cache.as_ref().map(ApiCache::ttl);
 
-        res.json::<PackVersionData>().await
+        Ok(self.get(&format!("public/modpack/search/{}?term={}", limit, term), ttl).await?.0)
+    }
+
+    /// Get modpack manifest using pack id
+    pub async fn modpack_manifest(&self, pack_id: u32) -> APIResult<ModPack> {
+        self.get_manifest(&format!("public/modpack/{}", pack_id)).await
+    }
+
+    /// Get modpack version data using pack id and version id.
+    /// Cached version data is kept until newer manifest says the version is updated.
+    pub async fn modpack_version_data(&self, pack_id: u32, version_id: u32) -> APIResult<PackVersionData> {
+        Ok(self.get(&format!("public/modpack/{}/{}", pack_id, version_id), None).await?.0)
     }
 
     /// Get curseforge modpack manifest using pack id
-    pub async fn curseforge_manifest(pack_id: u32) -> APIResult<ModPack> {
-        let res = get(api_endpoint(&format!("public/curseforge/{}", pack_id))).await?;
+    pub async fn curseforge_manifest(&self, pack_id: u32) -> APIResult<ModPack> {
+        self.get_manifest(&format!("public/curseforge/{}", pack_id)).await
+    }
 
-        res.json::<ModPack>().await
+    /// Get curseforge modpack version data using pack id and version id.
+    /// Cached version data is kept until newer manifest says the version is updated.
+    pub async fn curseforge_version_data(&self, pack_id: u32, version_id: u32) -> APIResult<PackVersionData> {
+        Ok(self.get(&format!("public/curseforge/{}/{}", pack_id, version_id), None).await?.0)
     }
 
-    /// Get curseforge modpack version data using pack id and version id
-    pub async fn curseforge_version_data(pack_id: u32, version_id: u32) -> APIResult<PackVersionData> {
-        let res = get(api_endpoint(&format!("public/curseforge/{}/{}", pack_id, version_id))).await?;
+    /// Get binary resource like pack art from url using cache. Api headers are not sent.
+    pub async fn resource(&self, url: &str) -> APIResult<Bytes> {
+        if let Some(cache) = &self.cache {
+            if let Some(body) = cache.load_resource(url).await {
+                return Ok(body.into());
+            }
+
+            if cache.offline() {
+                return Err(ModPackApiError::NotCached(url.into()));
+            }
+        }
 
-        res.json::<PackVersionData>().await
+        let res = self.client.get(url).send().await?;
+
+        let status = res.status();
+        if !status.is_success() {
+            return Err(ModPackApiError::Status(status, url.into()));
+        }
+
+        let body = res.bytes().await?;
+
+        if let Some(cache) = &self.cache {
+            // Cache is best effort
+            cache.store_resource(url, &body).await.ok();
+        }
+
+        Ok(body)
     }
 
 }
+
+impl Default for ModPackAPI {
+    fn default() -> Self {
+        Self::new()
+    }
+}
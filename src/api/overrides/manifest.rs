@@ -20,6 +20,9 @@ pub struct PackManifest {
     /// Optional overrides directory name
     pub overrides: Option<String>,
 
+    /// Pack name
+    pub name: String,
+
     /// Semantic pack version
     pub version: String,
 
@@ -27,6 +30,7 @@ pub struct PackManifest {
     pub author: String,
 
     /// Pack description
+    #[serde(default)]
     pub description: String,
 
     /// File list
@@ -66,6 +70,18 @@ pub struct PackMC {
 
 }
 
+impl PackMC {
+
+    /// Primary modloader. Returns first one if none of them are marked as primary.
+    pub fn primary_modloader(&self) -> Option<&PackModLoader> {
+        self.modloaders
+            .iter()
+            .find(|loader| loader.primary.unwrap_or(false))
+            .or_else(|| self.modloaders.first())
+    }
+
+}
+
 /// Modpack modloader configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PackModLoader {
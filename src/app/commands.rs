@@ -0,0 +1,1006 @@
+/*
+ * Created on Sun May 23 2021
+ *
+ * Copyright (c) storycraft. Licensed under the GNU General Public License v3.
+ */
+
+//! Non interactive app commands
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use dialoguer::{Confirm, Select};
+use humansize::{file_size_opts, FileSize};
+use serde::Serialize;
+use tokio::task;
+
+use crate::{
+    api::modpack::{
+        data::{PackFile, PackFileType, PackVersionData},
+        info::PackVersion,
+        ModPackAPI,
+    },
+    launcher::default_minecraft_dir,
+};
+
+use super::{
+    config::AppConfig,
+    confirm_incomplete_install, fetch_pack_icon, update_pack_files, fetch_version_data, install_bundle_files, download_pack_files, install_pack_files,
+    install_pack_profile, install_pack_profile_icon, launch_targets, load_launcher_profile,
+    pack_install::{
+        bundle::{read_bundle, write_bundle, BundleManifest},
+        plan::FilePlan,
+        receipt::{InstallReceipt, PackSource, ReceiptFile},
+        report::InstallReport,
+        store::ContentStore,
+        uninstall::{is_instance_profile, remove_instance, remove_pack_files},
+        update::{ConfigAction, FileDiff},
+        verify::{verify_files, VerifyReport},
+    },
+    pack_select::{create_list_from_result, ModPackVariant, TaskError},
+    print_file_diff, print_file_plan, print_install_info, profile_icon, remove_game_profile, print_modloader_notice, print_pack_info, select_files,
+    ver_select::{sort_versions, PackVersionSelect},
+    AppError,
+};
+
+/// Modpack summary for machine readable output
+#[derive(Debug, Clone, Serialize)]
+pub struct PackSummary {
+    /// Modpack id
+    pub id: u32,
+
+    /// true if the id is curseforge pack id
+    pub curseforge: bool,
+
+    /// The name of the modpack
+    pub name: String,
+
+    /// Short description about this modpack
+    pub synopsis: Option<String>,
+
+    /// Modpack authors
+    pub authors: String,
+
+    /// Modpack tag names
+    pub tags: Vec<String>,
+
+    /// Modpack download count
+    pub installs: i64,
+
+    /// Last pack update time
+    pub updated: u32,
+}
+
+impl From<&ModPackVariant> for PackSummary {
+    fn from(pack: &ModPackVariant) -> Self {
+        let info = pack.info();
+
+        Self {
+            id: info.id,
+            curseforge: matches!(pack, ModPackVariant::CurseForge(_)),
+            name: info.name.clone(),
+            synopsis: info.synopsis.clone(),
+            authors: info.author_str(),
+            tags: info.tags.iter().map(|tag| tag.name.clone()).collect(),
+            installs: info.installs,
+            updated: info.updated,
+        }
+    }
+}
+
+/// Pack version install plan
+#[derive(Debug, Clone, Serialize)]
+pub struct InstallPlan {
+    /// Pack to install
+    pub pack: PackSummary,
+
+    /// Version id
+    pub version_id: u32,
+
+    /// Version name
+    pub version_name: String,
+
+    /// Install location
+    pub location: PathBuf,
+
+    /// Total size of files to install (byte). Unknown sizes are not counted.
+    pub total_size: u64,
+
+    /// Files to install
+    pub files: Vec<PackFile>,
+}
+
+impl InstallPlan {
+    pub fn new(pack: &ModPackVariant, ver: &PackVersionData, location: PathBuf, files: Vec<PackFile>) -> Self {
+        Self {
+            pack: pack.into(),
+            version_id: ver.id,
+            version_name: ver.name.clone(),
+            location,
+            total_size: files.iter().filter_map(|file| file.info.size).sum(),
+            files,
+        }
+    }
+}
+
+/// Install command output
+#[derive(Debug, Clone, Serialize)]
+pub struct InstallOutput {
+    /// Install plan
+    pub plan: InstallPlan,
+
+    /// Install plan checked against disk. Only exists on dry run.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub check: Option<FilePlan>,
+
+    /// Install report. Doesn't exist on dry run.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub report: Option<InstallReport>,
+}
+
+/// Update command output
+#[derive(Debug, Clone, Serialize)]
+pub struct UpdateOutput {
+    /// Previously installed version id
+    pub from_version_id: u32,
+
+    /// Updated version id
+    pub version_id: u32,
+
+    /// Updated version name
+    pub version_name: String,
+
+    /// File difference
+    pub diff: FileDiff,
+
+    /// Changed config files and replaced extracted files modified since installed
+    pub modified_configs: Vec<ReceiptFile>,
+
+    /// Install report of changed files. Doesn't exist on dry run.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub report: Option<InstallReport>,
+}
+
+/// Uninstall command output
+#[derive(Debug, Clone, Serialize)]
+pub struct UninstallOutput {
+    /// Pack files to delete
+    pub files: Vec<ReceiptFile>,
+
+    /// Deleted file count. Doesn't exist on dry run.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub removed_files: Option<usize>,
+
+    /// true if the launcher profile was removed
+    pub removed_profile: bool,
+
+    /// true if the install location was removed
+    pub removed_dir: bool,
+}
+
+/// Verify command output
+#[derive(Debug, Clone, Serialize)]
+pub struct VerifyOutput {
+    /// Verification result
+    pub verify: VerifyReport,
+
+    /// Repair install report. Only exists if repaired.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub report: Option<InstallReport>,
+}
+
+/// Export bundle command output
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportOutput {
+    /// Exported pack
+    pub pack: PackSummary,
+
+    /// Version id
+    pub version_id: u32,
+
+    /// Version name
+    pub version_name: String,
+
+    /// Bundle file path. Doesn't exist if download failed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bundle: Option<PathBuf>,
+
+    /// Download report of bundled files
+    pub report: InstallReport,
+}
+
+/// Print value as pretty json
+pub fn print_json<T: Serialize + ?Sized>(value: &T) -> Result<(), AppError> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+
+    Ok(())
+}
+
+/// Options of install command
+#[derive(Debug, Clone, Default)]
+pub struct InstallOptions {
+    /// Pack id to install
+    pub pack_id: u32,
+
+    /// true if the pack id is curseforge pack id
+    pub curseforge: bool,
+
+    /// Version id or name to install. Latest version is used if none.
+    pub version: Option<String>,
+
+    /// Install directory. Defaults to `modpacks/<pack name>` in minecraft directory.
+    pub dir: Option<PathBuf>,
+
+    /// Minecraft data directory. Defaults to platform specific minecraft directory.
+    pub mc_dir: Option<PathBuf>,
+
+    /// Install optional files
+    pub optional: bool,
+
+    /// Skip every confirmation
+    pub assume_yes: bool,
+
+    /// Print plan and report as json instead of progress
+    pub json: bool,
+
+    /// Print install plan checked against disk and exit without installing
+    pub dry_run: bool,
+
+    /// Bundle to install from instead of downloading. Pack id, curseforge and version are ignored if set.
+    pub bundle: Option<PathBuf>,
+}
+
+/// Options of export bundle command
+#[derive(Debug, Clone, Default)]
+pub struct ExportOptions {
+    /// Pack id to export
+    pub pack_id: u32,
+
+    /// true if the pack id is curseforge pack id
+    pub curseforge: bool,
+
+    /// Version id or name to export. Latest version is used if none.
+    pub version: Option<String>,
+
+    /// Include optional files
+    pub optional: bool,
+
+    /// Bundle file path
+    pub output: PathBuf,
+
+    /// Print bundle summary and report as json instead of progress
+    pub json: bool,
+}
+
+/// Options of update command
+#[derive(Debug, Clone, Default)]
+pub struct UpdateOptions {
+    /// Install location of pack to update
+    pub dir: PathBuf,
+
+    /// Version id or name to update to. Latest version is used if none.
+    pub version: Option<String>,
+
+    /// Minecraft data directory. Defaults to platform specific minecraft directory.
+    pub mc_dir: Option<PathBuf>,
+
+    /// Install optional files not installed previously
+    pub optional: bool,
+
+    /// Skip every confirmation
+    pub assume_yes: bool,
+
+    /// Print diff and report as json instead of progress
+    pub json: bool,
+
+    /// Print file difference and exit without updating
+    pub dry_run: bool,
+
+    /// Action for config files modified since installed. Asks for each file if none and interactive, keeps otherwise.
+    pub config_action: Option<ConfigAction>,
+}
+
+/// Options of uninstall command
+#[derive(Debug, Clone, Default)]
+pub struct UninstallOptions {
+    /// Install location of pack to uninstall
+    pub dir: PathBuf,
+
+    /// Minecraft data directory. Defaults to platform specific minecraft directory.
+    pub mc_dir: Option<PathBuf>,
+
+    /// Delete whole install location instead of pack files only
+    pub all: bool,
+
+    /// Delete saves too when deleting whole install location
+    pub force: bool,
+
+    /// Skip every confirmation
+    pub assume_yes: bool,
+
+    /// Print result as json
+    pub json: bool,
+
+    /// Print files to delete and exit without uninstalling
+    pub dry_run: bool,
+}
+
+/// Fetch pack using pack id
+pub async fn fetch_pack(api: &ModPackAPI, pack_id: u32, curseforge: bool) -> Result<ModPackVariant, AppError> {
+    if curseforge {
+        Ok(ModPackVariant::CurseForge(api.curseforge_manifest(pack_id).await?))
+    } else {
+        Ok(ModPackVariant::ModPack(api.modpack_manifest(pack_id).await?))
+    }
+}
+
+/// Find version by id or name. Returns latest version if version is none.
+pub fn find_version(versions: &[PackVersion], version: Option<&str>) -> Option<PackVersion> {
+    let mut list = versions.to_vec();
+    sort_versions(&mut list);
+
+    match version {
+        Some(version) => list
+            .into_iter()
+            .find(|ver| ver.id.to_string() == version || ver.name == version),
+
+        None => list.into_iter().next(),
+    }
+}
+
+/// Search modpacks and print results
+pub async fn search(api: &ModPackAPI, term: &str, limit: u32, config: &AppConfig, json: bool) -> Result<(), AppError> {
+    let result = api.search(term, limit).await?;
+    let list = create_list_from_result(api, result, config.fetch_concurrency).await?;
+
+    if json {
+        return print_json(&list.iter().map(PackSummary::from).collect::<Vec<PackSummary>>());
+    }
+
+    for pack in &list {
+        println!("{}", pack.display());
+    }
+
+    Ok(())
+}
+
+/// Print modpack information
+pub async fn info(api: &ModPackAPI, pack_id: u32, curseforge: bool, json: bool) -> Result<(), AppError> {
+    let pack = fetch_pack(api, pack_id, curseforge).await?;
+
+    if json {
+        return print_json(pack.info());
+    }
+
+    print_pack_info(pack.info());
+
+    Ok(())
+}
+
+/// Print modpack versions from latest to oldest
+pub async fn versions(api: &ModPackAPI, pack_id: u32, curseforge: bool, json: bool) -> Result<(), AppError> {
+    let pack = fetch_pack(api, pack_id, curseforge).await?;
+
+    let mut list = pack.info().versions.clone();
+    sort_versions(&mut list);
+
+    if json {
+        return print_json(&list);
+    }
+
+    for version in &list {
+        println!("{}", PackVersionSelect::version_desc(version));
+    }
+
+    Ok(())
+}
+
+/// Install modpack without interaction.
+/// Json output never asks anything. Dry run exits after printing plan without touching disk.
+pub async fn install(api: &ModPackAPI, options: InstallOptions, config: &AppConfig) -> Result<(), AppError> {
+    let interactive = !options.assume_yes && !options.json;
+
+    let data_path = options.mc_dir.clone().unwrap_or_else(default_minecraft_dir);
+    let launcher_profile = load_launcher_profile(&data_path)?;
+
+    let (pack, ver, icon) = match &options.bundle {
+        Some(bundle) => {
+            let (manifest, icon) = read_bundle(bundle)?;
+
+            (manifest.pack_variant(), manifest.version, icon)
+        }
+
+        None => {
+            let pack = fetch_pack(api, options.pack_id, options.curseforge).await?;
+
+            let version = find_version(&pack.info().versions, options.version.as_deref()).ok_or(AppError::InvalidPack)?;
+            let ver = fetch_version_data(api, &pack, version.id).await?;
+
+            (pack, ver, None)
+        }
+    };
+    let info = pack.info();
+
+    let install_location = options
+        .dir
+        .clone()
+        .unwrap_or_else(|| data_path.join("modpacks").join(&info.name));
+
+    if !options.json {
+        print_install_info(info, &ver, &install_location);
+    }
+
+    let plan = InstallPlan::new(&pack, &ver, install_location.clone(), select_files(&ver.files, options.optional));
+
+    if options.dry_run {
+        let check = FilePlan::check(&plan.files, &install_location)?;
+
+        if options.json {
+            return print_json(&InstallOutput { plan, check: Some(check), report: None });
+        }
+
+        print_file_plan(&check);
+
+        return Ok(());
+    }
+
+    if interactive {
+        let mut confirm = Confirm::new();
+        confirm.with_prompt("Install?");
+        if !confirm.interact()? {
+            return Err(AppError::Cancelled);
+        }
+    }
+
+    let report = match &options.bundle {
+        Some(bundle) => install_bundle_files(bundle, plan.files.clone(), &install_location, config, !options.json).await?,
+        None => install_pack_files(plan.files.clone(), &install_location, config, !options.json).await?,
+    };
+
+    // Report is printed even if the install failed
+    if options.json {
+        print_json(&InstallOutput { plan, check: None, report: Some(report.clone()) })?;
+    }
+
+    // Receipt is saved before asking so failed install can be updated, verified or uninstalled
+    InstallReceipt::new(&pack, &ver, &report).save(&install_location)?;
+
+    confirm_incomplete_install(&report, interactive)?;
+
+    if !options.json {
+        println!("{}", console::style("Installing pack profile...").yellow());
+
+        let (game, modloader) = launch_targets(&ver)?;
+        print_modloader_notice(&game.version, &modloader.name, &modloader.version);
+    }
+
+    match &options.bundle {
+        // Bundle is installed without network
+        Some(_) => {
+            let icon = profile_icon(icon.as_deref());
+
+            install_pack_profile_icon(&data_path, &launcher_profile, info, &ver, &install_location, icon).await?
+        }
+
+        None => install_pack_profile(api, &data_path, &launcher_profile, info, &ver, &install_location).await?,
+    }
+
+    if !report.is_complete() {
+        return Err(AppError::IncompleteInstall(report.failed().count()));
+    }
+
+    if !options.json {
+        println!("{}", console::style("Finished installing modpack. Adjust game profile manually for proper launch.").green());
+    }
+
+    Ok(())
+}
+
+/// Download pack version files and write them into bundle with version data and icon for offline install
+pub async fn export_bundle(api: &ModPackAPI, options: ExportOptions, config: &AppConfig) -> Result<(), AppError> {
+    let pack = fetch_pack(api, options.pack_id, options.curseforge).await?;
+    let info = pack.info();
+
+    let version = find_version(&info.versions, options.version.as_deref()).ok_or(AppError::InvalidPack)?;
+    let mut ver = fetch_version_data(api, &pack, version.id).await?;
+
+    // Bundle version data only contains bundled files
+    ver.files = select_files(&ver.files, options.optional);
+
+    if !options.json {
+        println!(
+            "Exporting {} {} to {}",
+            console::style(&info.name).green(),
+            console::style(&ver.name).yellow(),
+            options.output.to_string_lossy()
+        );
+    }
+
+    // Files are downloaded next to bundle first so interrupted export can be resumed
+    let staging = PathBuf::from(format!("{}.files", options.output.to_string_lossy()));
+
+    // Overrides package is bundled as is and extracted on install
+    let report = download_pack_files(ver.files.clone(), &staging, config, !options.json).await?;
+
+    let mut output = ExportOutput {
+        pack: (&pack).into(),
+        version_id: ver.id,
+        version_name: ver.name.clone(),
+        bundle: None,
+        report,
+    };
+
+    if !output.report.is_complete() {
+        if options.json {
+            print_json(&output)?;
+        }
+
+        return Err(AppError::IncompleteInstall(output.report.failed().count()));
+    }
+
+    let icon = fetch_pack_icon(api, info).await;
+    let manifest = BundleManifest {
+        source: (&pack).into(),
+        pack: info.clone(),
+        version: ver,
+    };
+
+    {
+        let (output, staging) = (options.output.clone(), staging.clone());
+
+        task::spawn_blocking(move || write_bundle(&output, &manifest, icon.as_deref(), &staging))
+            .await
+            .map_err(TaskError::from)??;
+    }
+
+    fs::remove_dir_all(&staging)?;
+
+    if options.json {
+        output.bundle = Some(options.output);
+
+        return print_json(&output);
+    }
+
+    println!("{}", console::style("Finished exporting bundle.").green());
+
+    Ok(())
+}
+
+/// Update installed pack to another version.
+/// Only added or changed files are downloaded and files removed from the pack are deleted.
+/// Files not installed by the pack are left untouched.
+pub async fn update(api: &ModPackAPI, options: UpdateOptions, config: &AppConfig) -> Result<(), AppError> {
+    let interactive = !options.assume_yes && !options.json;
+
+    let receipt = load_receipt(&options.dir)?;
+
+    if receipt.source == PackSource::Package {
+        return Err(AppError::PackageUnsupported("Update"));
+    }
+
+    let data_path = options.mc_dir.clone().unwrap_or_else(default_minecraft_dir);
+    let launcher_profile = load_launcher_profile(&data_path)?;
+
+    let pack = fetch_pack(api, receipt.pack_id, receipt.source == PackSource::CurseForge).await?;
+    let info = pack.info();
+
+    let version = find_version(&info.versions, options.version.as_deref()).ok_or(AppError::InvalidPack)?;
+    let ver = fetch_version_data(api, &pack, version.id).await?;
+
+    // Optional files installed previously stay installed
+    let installed_paths = receipt
+        .files
+        .iter()
+        .filter_map(ReceiptFile::install_path)
+        .collect::<Vec<PathBuf>>();
+
+    let files = ver
+        .files
+        .iter()
+        .filter(|file| {
+            options.optional
+                || !file.info.optional
+                || file.info.install_path().is_some_and(|path| installed_paths.contains(&path))
+        })
+        .cloned()
+        .collect::<Vec<PackFile>>();
+
+    let mut diff = FileDiff::new(&receipt.files, &files);
+    let modified = diff.modified_configs(&receipt.files, &options.dir);
+    let modified_configs = modified.iter().map(|config| config.previous.clone()).collect::<Vec<ReceiptFile>>();
+
+    if !options.json {
+        println!(
+            "Updating {} {} -> {}",
+            console::style(&info.name).green(),
+            console::style(&receipt.version_name).yellow(),
+            console::style(&ver.name).yellow()
+        );
+
+        print_file_diff(&diff);
+
+        for previous in &modified_configs {
+            println!(
+                "{} {}",
+                console::style("modified").magenta(),
+                Path::new(&previous.path).join(&previous.name).to_string_lossy()
+            );
+        }
+    }
+
+    if options.dry_run {
+        if options.json {
+            return print_json(&UpdateOutput {
+                from_version_id: receipt.version_id,
+                version_id: ver.id,
+                version_name: ver.name.clone(),
+                diff,
+                modified_configs,
+                report: None,
+            });
+        }
+
+        return Ok(());
+    }
+
+    for config in modified {
+        let action = match options.config_action {
+            Some(action) => action,
+            None if interactive => ask_config_action(&config.previous)?,
+            None => ConfigAction::Keep,
+        };
+
+        diff.resolve_config(config, action);
+    }
+
+    if interactive {
+        let mut confirm = Confirm::new();
+        confirm.with_prompt("Update?");
+        if !confirm.interact()? {
+            return Err(AppError::Cancelled);
+        }
+    }
+
+    let report = update_pack_files(
+        diff.downloads(),
+        &options.dir,
+        config,
+        !options.json,
+        diff.extract_actions.clone(),
+    )
+    .await?;
+
+    let mut new_receipt = InstallReceipt::new(&pack, &ver, &report);
+    new_receipt.files.extend(diff.unchanged.iter().map(ReceiptFile::from));
+    new_receipt.files.extend(diff.kept.iter().cloned());
+    if !diff.overrides_changed() {
+        new_receipt.files.extend(diff.extracted.iter().cloned());
+    }
+
+    // Stale files are removed only after every download succeeded so failed update can run again
+    if report.is_complete() {
+        diff.remove_files(&options.dir, &new_receipt.files)?;
+    } else {
+        let stale = diff.remaining_stale_files(&new_receipt.files);
+        new_receipt.files.extend(stale);
+    }
+
+    new_receipt.save(&options.dir)?;
+
+    if options.json {
+        print_json(&UpdateOutput {
+            from_version_id: receipt.version_id,
+            version_id: ver.id,
+            version_name: ver.name.clone(),
+            diff,
+            modified_configs,
+            report: Some(report.clone()),
+        })?;
+    }
+
+    confirm_incomplete_install(&report, interactive)?;
+
+    if !options.json {
+        println!("{}", console::style("Updating pack profile...").yellow());
+
+        let (game, modloader) = launch_targets(&ver)?;
+        print_modloader_notice(&game.version, &modloader.name, &modloader.version);
+    }
+
+    install_pack_profile(api, &data_path, &launcher_profile, info, &ver, &options.dir).await?;
+
+    if !report.is_complete() {
+        return Err(AppError::IncompleteInstall(report.failed().count()));
+    }
+
+    if !options.json {
+        println!("{}", console::style("Finished updating modpack.").green());
+    }
+
+    Ok(())
+}
+
+/// Uninstall pack using install receipt and remove its launcher profile.
+/// Only pack files are deleted unless whole install location deletion is requested.
+pub async fn uninstall(options: UninstallOptions) -> Result<(), AppError> {
+    let receipt = load_receipt(&options.dir)?;
+
+    let data_path = options.mc_dir.clone().unwrap_or_else(default_minecraft_dir);
+    let launcher_profile = load_launcher_profile(&data_path)?;
+
+    if !options.json {
+        println!(
+            "Uninstalling {} {} from {}",
+            console::style(&receipt.name).green(),
+            console::style(&receipt.version_name).yellow(),
+            console::style(options.dir.to_string_lossy()).yellow()
+        );
+    }
+
+    if options.dry_run {
+        if options.json {
+            return print_json(&UninstallOutput {
+                files: receipt.files,
+                removed_files: None,
+                removed_profile: false,
+                removed_dir: false,
+            });
+        }
+
+        for file in &receipt.files {
+            println!(
+                "{} {}",
+                console::style("remove").red(),
+                Path::new(&file.path).join(&file.name).to_string_lossy()
+            );
+        }
+
+        return Ok(());
+    }
+
+    if !options.assume_yes && !options.json {
+        let mut confirm = Confirm::new();
+        confirm.with_prompt(if options.all && options.force {
+            "Delete whole install location including saves?"
+        } else if options.all {
+            "Delete whole install location except saves?"
+        } else {
+            "Delete pack files?"
+        });
+        confirm.default(false);
+
+        if !confirm.interact()? {
+            return Err(AppError::Cancelled);
+        }
+    }
+
+    // Checked before deleting since install location may not exist after
+    let profile_key = receipt.profile_key();
+    let owns_profile = launcher_profile
+        .profiles
+        .get(&profile_key)
+        .is_some_and(|profile| is_instance_profile(profile, &options.dir));
+
+    let removed_files = remove_pack_files(&options.dir, &receipt.files)?;
+
+    if options.all {
+        remove_instance(&options.dir, !options.force)?;
+    }
+
+    let removed_profile = if owns_profile {
+        remove_game_profile(&data_path, &launcher_profile, &profile_key).await?
+    } else {
+        false
+    };
+
+    if options.json {
+        return print_json(&UninstallOutput {
+            files: receipt.files,
+            removed_files: Some(removed_files),
+            removed_profile,
+            removed_dir: options.all,
+        });
+    }
+
+    println!(
+        "{}",
+        console::style(format!("Deleted {} files. Finished uninstalling modpack.", removed_files)).green()
+    );
+
+    Ok(())
+}
+
+/// Verify installed files of install location and repair missing or corrupt files if repair is true.
+/// Config files modified by user are repaired only if overwrite_modified is true.
+pub async fn verify(
+    api: &ModPackAPI,
+    dir: PathBuf,
+    repair: bool,
+    overwrite_modified: bool,
+    config: &AppConfig,
+    json: bool,
+) -> Result<(), AppError> {
+    let receipt = load_receipt(&dir)?;
+
+    if !json {
+        println!(
+            "Verifying {} {}...",
+            console::style(&receipt.name).green(),
+            console::style(&receipt.version_name).yellow()
+        );
+
+        if receipt.incomplete {
+            println!("{}", console::style("Previous install was incomplete. Files failed to install are not verified.").yellow());
+        }
+    }
+
+    let concurrency = std::thread::available_parallelism().map_or(1, |count| count.get());
+    let verify_report = verify_files(dir.clone(), receipt.files.clone(), concurrency).await?;
+
+    let bad = verify_report.bad().map(|verified| verified.file.clone()).collect::<Vec<ReceiptFile>>();
+    let modified = verify_report.modified().map(|verified| verified.file.clone()).collect::<Vec<ReceiptFile>>();
+
+    if !json {
+        for verified in verify_report.bad() {
+            println!(
+                "{} {}",
+                console::style(format!("{:?}", verified.state).to_lowercase()).red(),
+                Path::new(&verified.file.path).join(&verified.file.name).to_string_lossy()
+            );
+        }
+
+        for file in &modified {
+            println!(
+                "{} {}",
+                console::style("modified").magenta(),
+                Path::new(&file.path).join(&file.name).to_string_lossy()
+            );
+        }
+
+        for path in &verify_report.extra_files {
+            println!("{} {}", console::style("extra").cyan(), path.to_string_lossy());
+        }
+
+        println!(
+            "{} valid, {} missing or corrupt, {} modified, {} extra",
+            console::style(verify_report.files.len() - bad.len() - modified.len()).green(),
+            console::style(bad.len()).red(),
+            console::style(modified.len()).magenta(),
+            console::style(verify_report.extra_files.len()).cyan()
+        );
+    }
+
+    // Modified config files are user changes, not errors
+    let repair_files = if overwrite_modified {
+        bad.iter().chain(&modified).cloned().collect::<Vec<ReceiptFile>>()
+    } else {
+        bad.clone()
+    };
+
+    if !repair || repair_files.is_empty() {
+        if json {
+            print_json(&VerifyOutput { verify: verify_report, report: None })?;
+        }
+
+        if !bad.is_empty() {
+            return Err(AppError::Corrupted(bad.len()));
+        }
+
+        return Ok(());
+    }
+
+    // Pack files are fetched again since receipt doesn't have download information
+    if receipt.source == PackSource::Package {
+        return Err(AppError::PackageUnsupported("Repair"));
+    }
+
+    let pack = fetch_pack(api, receipt.pack_id, receipt.source == PackSource::CurseForge).await?;
+    let ver = fetch_version_data(api, &pack, receipt.version_id).await?;
+
+    // Extracted files are repaired by extracting overrides again
+    let (bad_extracted, bad) = repair_files.into_iter().partition::<Vec<_>, _>(|file| file.extracted);
+
+    let bad_paths = bad.iter().filter_map(ReceiptFile::install_path).collect::<Vec<PathBuf>>();
+    let files = ver
+        .files
+        .into_iter()
+        .filter(|file| {
+            (!bad_extracted.is_empty() && matches!(file.file_type, PackFileType::Overrides))
+                || file.info.install_path().is_some_and(|path| bad_paths.contains(&path))
+        })
+        .collect::<Vec<PackFile>>();
+
+    let repaired_paths = files.iter().filter_map(|file| file.info.install_path()).collect::<Vec<PathBuf>>();
+    let has_overrides = files.iter().any(|file| matches!(file.file_type, PackFileType::Overrides));
+
+    let mut unrepairable = bad
+        .iter()
+        .filter(|file| !file.install_path().is_some_and(|path| repaired_paths.contains(&path)))
+        .count();
+
+    if has_overrides {
+        // Modified extracted files are kept on extraction so they are removed first
+        for install_path in bad_extracted.iter().filter_map(ReceiptFile::install_path) {
+            match fs::remove_file(dir.join(install_path)) {
+                Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err.into()),
+                _ => {}
+            }
+        }
+    } else {
+        unrepairable += bad_extracted.len();
+    }
+
+    let report = install_pack_files(files, &dir, config, !json).await?;
+
+    if json {
+        print_json(&VerifyOutput { verify: verify_report, report: Some(report.clone()) })?;
+    }
+
+    if !report.is_complete() {
+        return Err(AppError::IncompleteInstall(report.failed().count()));
+    }
+
+    if unrepairable > 0 {
+        return Err(AppError::Corrupted(unrepairable));
+    }
+
+    if !json {
+        println!("{}", console::style("Finished repairing modpack.").green());
+    }
+
+    Ok(())
+}
+
+/// Remove content store files not used by any registered install location
+pub async fn gc(config: &AppConfig, dry_run: bool, json: bool) -> Result<(), AppError> {
+    let dir = config
+        .content_store_dir()
+        .ok_or_else(|| AppError::InvalidConfig("cannot find content store directory".into()))?;
+    let store = ContentStore::new(dir);
+
+    let report = store.gc(dry_run)?;
+
+    if json {
+        return print_json(&report);
+    }
+
+    println!(
+        "{} {} files ({}), kept {} files",
+        if dry_run { "Would remove" } else { "Removed" },
+        console::style(report.removed).red(),
+        console::style(report.removed_bytes.file_size(file_size_opts::BINARY).unwrap()).red(),
+        console::style(report.kept).green()
+    );
+
+    Ok(())
+}
+
+/// Read install receipt of install location
+fn load_receipt(location: &Path) -> Result<InstallReceipt, AppError> {
+    match InstallReceipt::load(location) {
+        Ok(receipt) => Ok(receipt),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Err(AppError::MissingReceipt),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Ask action for config file modified since installed
+fn ask_config_action(previous: &ReceiptFile) -> Result<ConfigAction, AppError> {
+    let actions = [ConfigAction::Keep, ConfigAction::Overwrite, ConfigAction::Backup];
+
+    let mut sel = Select::new();
+    sel.with_prompt(format!(
+        "{} is modified. Update it?",
+        Path::new(&previous.path).join(&previous.name).to_string_lossy()
+    ));
+    sel.items(&[
+        "Keep modified file",
+        "Overwrite with pack file",
+        "Keep modified file and save pack file as .new",
+    ]);
+    sel.default(0);
+
+    Ok(actions[sel.interact()?])
+}
@@ -0,0 +1,282 @@
+/*
+ * Created on Sat May 22 2021
+ *
+ * Copyright (c) storycraft. Licensed under the GNU General Public License v3.
+ */
+
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, BufReader},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use directories::ProjectDirs;
+use reqwest::{
+    header::{HeaderMap, HeaderName, HeaderValue},
+    Client, Proxy,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api::{
+        cache::ApiCache,
+        curseforge::{CurseForgeAPI, CURSEFORGE_API_URL},
+        modpack::{ModPackAPI, API_URL},
+    },
+    util::bandwidth::BandwidthLimiter,
+};
+
+use super::{
+    pack_install::{store::ContentStore, web::RetryPolicy},
+    AppError,
+};
+
+/// Config file name in config directory
+pub const CONFIG_FILE: &str = "config.json";
+
+/// App configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct AppConfig {
+    /// Maximum concurrent file downloads
+    pub download_concurrency: usize,
+
+    /// Maximum concurrent api manifest fetches
+    pub fetch_concurrency: usize,
+
+    /// Total download bandwidth limit (byte/s). Unlimited if none.
+    pub bandwidth_limit: Option<u64>,
+
+    /// Maximum retry count of failed download
+    pub max_retries: u32,
+
+    /// modpacks.ch compatible api base url
+    pub api_url: String,
+
+    /// Headers added to every api request (auth headers etc.)
+    pub api_headers: HashMap<String, String>,
+
+    /// Curseforge api base url used to resolve package files
+    pub curseforge_api_url: String,
+
+    /// Curseforge api key used to resolve package files
+    pub curseforge_api_key: Option<String>,
+
+    /// Http user agent
+    pub user_agent: String,
+
+    /// Http proxy url used for every request. Uses system proxy if none.
+    pub proxy: Option<String>,
+
+    /// Connection timeout (second)
+    pub connect_timeout: u64,
+
+    /// Whole request timeout including body (second). No timeout if none.
+    pub timeout: Option<u64>,
+
+    /// Cache api responses in user cache directory
+    pub cache: bool,
+
+    /// Time to live of cached search results and manifests (second)
+    pub cache_ttl: u64,
+
+    /// Serve api responses only from cache
+    pub offline: bool,
+
+    /// Share installed files between instances using content store
+    pub store: bool,
+
+    /// Content store directory. Uses user data directory if none.
+    pub store_dir: Option<PathBuf>,
+}
+
+impl AppConfig {
+    /// Platform specific default config file path
+    pub fn default_path() -> Option<PathBuf> {
+        ProjectDirs::from("sh", "pancake", "modpack-installer")
+            .map(|dirs| dirs.config_dir().join(CONFIG_FILE))
+    }
+
+    /// Load config from path. Returns default config if the file doesn't exist.
+    pub fn load(path: &Path) -> Result<Self, AppError> {
+        match fs::File::open(path) {
+            Ok(file) => {
+                let config: Self = serde_json::from_reader(BufReader::new(file))
+                    .map_err(|err| AppError::InvalidConfig(format!("cannot parse {}. err: {}", path.display(), err)))?;
+
+                config.validate()?;
+
+                Ok(config)
+            }
+
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Check values command line options also reject
+    pub fn validate(&self) -> Result<(), AppError> {
+        if self.download_concurrency == 0 {
+            return Err(AppError::InvalidConfig("downloadConcurrency must be greater than 0".into()));
+        }
+
+        if self.fetch_concurrency == 0 {
+            return Err(AppError::InvalidConfig("fetchConcurrency must be greater than 0".into()));
+        }
+
+        if self.bandwidth_limit == Some(0) {
+            return Err(AppError::InvalidConfig("bandwidthLimit must be greater than 0".into()));
+        }
+
+        Ok(())
+    }
+
+    /// Download retry policy
+    pub fn retry_policy(&self) -> RetryPolicy {
+        RetryPolicy {
+            max_retries: self.max_retries,
+            ..Default::default()
+        }
+    }
+
+    /// Platform specific default api cache directory
+    pub fn default_cache_dir() -> Option<PathBuf> {
+        ProjectDirs::from("sh", "pancake", "modpack-installer").map(|dirs| dirs.cache_dir().join("api"))
+    }
+
+    /// Create api response cache if cache is enabled or offline
+    pub fn api_cache(&self) -> Option<ApiCache> {
+        if !self.cache && !self.offline {
+            return None;
+        }
+
+        Self::default_cache_dir().map(|dir| ApiCache::new(dir, Duration::from_secs(self.cache_ttl), self.offline))
+    }
+
+    /// Content store directory. Returns configured directory or platform specific default.
+    pub fn content_store_dir(&self) -> Option<PathBuf> {
+        self.store_dir.clone().or_else(|| {
+            ProjectDirs::from("sh", "pancake", "modpack-installer").map(|dirs| dirs.data_dir().join("store"))
+        })
+    }
+
+    /// Create content store if store is enabled
+    pub fn content_store(&self) -> Option<ContentStore> {
+        if !self.store {
+            return None;
+        }
+
+        self.content_store_dir().map(ContentStore::new)
+    }
+
+    /// Create http client used for api requests and downloads
+    pub fn http_client(&self) -> Result<Client, AppError> {
+        let mut builder = Client::builder()
+            .user_agent(&self.user_agent)
+            .connect_timeout(Duration::from_secs(self.connect_timeout));
+
+        if let Some(timeout) = self.timeout {
+            builder = builder.timeout(Duration::from_secs(timeout));
+        }
+
+        if let Some(proxy) = &self.proxy {
+            builder = builder.proxy(Proxy::all(proxy)?);
+        }
+
+        Ok(builder.build()?)
+    }
+
+    /// Create modpacks.ch api client
+    pub fn api(&self) -> Result<ModPackAPI, AppError> {
+        let mut headers = HeaderMap::new();
+
+        for (name, value) in &self.api_headers {
+            let name = HeaderName::from_bytes(name.as_bytes())
+                .map_err(|_| AppError::InvalidConfig(format!("invalid api header name: {}", name)))?;
+            let value = HeaderValue::from_str(value)
+                .map_err(|_| AppError::InvalidConfig(format!("invalid api header value of {}", name)))?;
+
+            headers.insert(name, value);
+        }
+
+        Ok(ModPackAPI::new_cached(self.http_client()?, self.api_url.clone(), headers, self.api_cache()))
+    }
+
+    /// Create curseforge api client
+    pub fn curseforge_api(&self) -> Result<CurseForgeAPI, AppError> {
+        Ok(CurseForgeAPI::new_cached(
+            self.http_client()?,
+            self.curseforge_api_url.clone(),
+            self.curseforge_api_key.clone(),
+            self.api_cache(),
+        ))
+    }
+
+    /// Create bandwidth limiter if limit is set
+    pub fn bandwidth_limiter(&self) -> Option<BandwidthLimiter> {
+        self.bandwidth_limit.map(BandwidthLimiter::new)
+    }
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        Self {
+            download_concurrency: 60,
+            fetch_concurrency: 16,
+            bandwidth_limit: None,
+            max_retries: RetryPolicy::default().max_retries,
+            api_url: API_URL.into(),
+            api_headers: HashMap::new(),
+            curseforge_api_url: CURSEFORGE_API_URL.into(),
+            curseforge_api_key: None,
+            user_agent: format!("modpack-installer/{}", env!("CARGO_PKG_VERSION")),
+            proxy: None,
+            connect_timeout: 10,
+            timeout: None,
+            cache: true,
+            cache_ttl: 3600,
+            offline: false,
+            store: false,
+            store_dir: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::app::AppError;
+
+    use super::AppConfig;
+
+    fn load(json: &str) -> Result<AppConfig, AppError> {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.json");
+        fs::write(&path, json).unwrap();
+
+        AppConfig::load(&path)
+    }
+
+    #[test]
+    fn load_valid_config() {
+        let config = load(r#"{ "downloadConcurrency": 4, "bandwidthLimit": 1024 }"#).unwrap();
+
+        assert_eq!(config.download_concurrency, 4);
+        assert_eq!(config.bandwidth_limit, Some(1024));
+    }
+
+    #[test]
+    fn reject_zero_values() {
+        for json in [
+            r#"{ "downloadConcurrency": 0 }"#,
+            r#"{ "fetchConcurrency": 0 }"#,
+            r#"{ "bandwidthLimit": 0 }"#,
+        ] {
+            assert!(matches!(load(json), Err(AppError::InvalidConfig(_))), "{}", json);
+        }
+    }
+}
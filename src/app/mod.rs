@@ -4,6 +4,8 @@
  * Copyright (c) storycraft. Licensed under the GNU General Public License v3.
  */
 
+pub mod config;
+pub mod commands;
 pub mod mc_data_dir_select;
 pub mod pack_install;
 pub mod pack_select;
@@ -11,30 +13,50 @@ pub mod tasks;
 pub mod ver_select;
 
 use std::{
+    collections::HashMap,
     error::Error,
     fmt::{self, Display, Formatter},
-    io,
-    path::PathBuf
+    io::{self, BufReader},
+    path::{Path, PathBuf},
+    sync::Arc,
 };
 
+use bytes::Bytes;
 use chrono::Utc;
 use dialoguer::{Confirm, Input};
+use humansize::{file_size_opts, FileSize};
 use indicatif::MultiProgress;
 use tokio::fs;
+use zip::ZipArchive;
 
 use crate::{
-    api::modpack::{
-        data::{PackFile, PackVersionData},
-        info::{ModPack, PackArt, PackArtType},
-        search::SearchResult,
-        ModPackAPI,
+    api::{
+        modpack::{
+            data::{PackFile, PackTarget, PackVersionData},
+            info::{ModPack, PackArtType},
+            search::SearchResult,
+            ModPackAPI, ModPackApiError,
+        },
+        overrides::manifest::PackManifest,
     },
     app::{tasks::install_pack::spawn_install_task, ver_select::PackVersionSelect},
-    launcher::profile::{GameLaunchProfile, LauncherProfile},
+    launcher::{
+        profile::{GameLaunchProfile, LauncherProfile},
+        LAUNCHER_PROFILE_FILE,
+    },
 };
 
 use self::{
+    config::AppConfig,
     mc_data_dir_select::MCDataDirSelect,
+    pack_install::{
+        package::{resolve_files, PackageInstallError, PackageInstaller},
+        plan::{FileAction, FilePlan},
+        receipt::InstallReceipt,
+        report::InstallReport,
+        source::{BundleSource, FileSource, HttpSource},
+        update::{ConfigAction, FileDiff},
+    },
     pack_select::{create_list_from_result, ModPackVariant, ModpackSelect, TaskError},
 };
 
@@ -43,11 +65,31 @@ use self::{
 pub enum AppError {
     Io(io::Error),
     Reqwest(reqwest::Error),
+    Api(ModPackApiError),
     Archive(zip::result::ZipError),
     Task(TaskError),
+    Package(PackageInstallError),
     Profile(serde_json::Error),
     InvalidPack,
     Cancelled,
+
+    /// Some files failed to install. (failed file count)
+    IncompleteInstall(usize),
+
+    /// Install location doesn't have install receipt
+    MissingReceipt,
+
+    /// Some installed files are missing or corrupt. (bad file count)
+    Corrupted(usize),
+
+    /// Config value is invalid. (reason)
+    InvalidConfig(String),
+
+    /// Interactive installer cannot print json output
+    JsonUnsupported,
+
+    /// Command needs pack files from api and install is from local package. (command)
+    PackageUnsupported(&'static str),
 }
 
 impl From<io::Error> for AppError {
@@ -62,12 +104,24 @@ impl From<reqwest::Error> for AppError {
     }
 }
 
+impl From<ModPackApiError> for AppError {
+    fn from(err: ModPackApiError) -> Self {
+        Self::Api(err)
+    }
+}
+
 impl From<TaskError> for AppError {
     fn from(err: TaskError) -> Self {
         Self::Task(err)
     }
 }
 
+impl From<PackageInstallError> for AppError {
+    fn from(err: PackageInstallError) -> Self {
+        Self::Package(err)
+    }
+}
+
 impl From<serde_json::Error> for AppError {
     fn from(err: serde_json::Error) -> Self {
         Self::Profile(err)
@@ -85,24 +139,32 @@ impl Display for AppError {
         match &self {
             AppError::Io(err) => err.fmt(f),
             AppError::Reqwest(err) => err.fmt(f),
+            AppError::Api(err) => err.fmt(f),
             AppError::Task(err) => err.fmt(f),
+            AppError::Package(err) => err.fmt(f),
             AppError::Profile(err) => err.fmt(f),
             AppError::Archive(err) => err.fmt(f),
             AppError::InvalidPack => writeln!(f, "Invalid pack to install"),
             AppError::Cancelled => writeln!(f, "Cancelled by user"),
+            AppError::IncompleteInstall(count) => writeln!(f, "{} files failed to install", count),
+            AppError::MissingReceipt => writeln!(f, "Install receipt not found. Not installed by modpack-installer"),
+            AppError::Corrupted(count) => writeln!(f, "{} files are missing or corrupt", count),
+            AppError::InvalidConfig(reason) => writeln!(f, "Invalid config. {}", reason),
+            AppError::JsonUnsupported => writeln!(f, "Interactive installer doesn't support --json. Use subcommands instead"),
+            AppError::PackageUnsupported(command) => writeln!(f, "{} is not supported for local package installs", command),
         }
     }
 }
 
 impl Error for AppError {}
 
-/// Main app
-pub async fn run() -> Result<(), AppError> {
+/// Main app. Prints install plan and exits without touching disk if dry_run is true.
+pub async fn run(api: &ModPackAPI, config: &AppConfig, dry_run: bool) -> Result<(), AppError> {
     // Select minecraft dir
     let (data_path, launcher_profile) = ask_minecraft_dir()?;
 
     // Ask pack
-    let pack = ask_pack_to_install().await?;
+    let pack = ask_pack_to_install(api, config.fetch_concurrency).await?;
     let info = pack.info();
 
     console::Term::stdout().clear_screen().unwrap_or_default();
@@ -110,7 +172,7 @@ pub async fn run() -> Result<(), AppError> {
     // Print pack info
     print_pack_info(info);
 
-    let ver = ask_pack_version(&pack).await?;
+    let ver = ask_pack_version(api, &pack).await?;
 
     if ver.is_none() {
         return Err(AppError::InvalidPack);
@@ -124,7 +186,12 @@ pub async fn run() -> Result<(), AppError> {
 
     print_install_info(info, &ver, &install_location);
 
-    let files = install_files(&ver)?;
+    let files = install_files(&ver.files)?;
+
+    if dry_run {
+        print_file_plan(&FilePlan::check(&files, &install_location)?);
+        return Ok(());
+    }
 
     let mut confirm = Confirm::new();
     confirm.with_prompt("Install?");
@@ -132,68 +199,109 @@ pub async fn run() -> Result<(), AppError> {
         return Err(AppError::Cancelled);
     }
 
-    let multi = MultiProgress::new();
-    let install_task_handle = spawn_install_task(files, install_location.clone(), &multi);
+    let report = install_pack_files(files, &install_location, config, true).await?;
 
-    multi.join()?;
-    if let Ok(res) = install_task_handle.await {
-        res?;
-    }
+    InstallReceipt::new(&pack, &ver, &report).save(&install_location)?;
+
+    confirm_incomplete_install(&report, true)?;
 
     println!("{}", console::style("Installing pack profile...").yellow());
 
-    let game_profile = {
-        let icon: String = create_profile_icon(&info).await;
-        let modloader = ver
-            .targets
-            .iter()
-            .filter(|target| target.target_type == "modloader")
-            .next()
-            .unwrap();
-
-        let game = ver
-            .targets
-            .iter()
-            .filter(|target| target.target_type == "game")
-            .next()
-            .unwrap();
+    let (game, modloader) = launch_targets(&ver)?;
+    print_modloader_notice(&game.version, &modloader.name, &modloader.version);
 
-        println!(
-            "{} {}",
-            console::style(format!("Install {} {} {} from", modloader.name, game.version, modloader.version)).yellow(),
-            console::style(format!("https://files.minecraftforge.net/net/minecraftforge/forge/index_{}.html", game.version)).yellow().bold()
-        );
+    install_pack_profile(api, &data_path, &launcher_profile, info, &ver, &install_location).await?;
 
+    if !report.is_complete() {
+        return Err(AppError::IncompleteInstall(report.failed().count()));
+    }
 
-        let time: String = Utc::now().to_string();
+    println!("{}",console::style("Finished installing modpack. Adjust game profile manually for proper launch.").green());
 
-        GameLaunchProfile {
-            created: Some(time.clone()),
-            last_used: Some(time.clone()),
-            game_dir: Some(install_location.to_string_lossy().into()),
-            java_args: None,
-            last_version_id: format!("Change this to valid {} {}-{} version", game.version, modloader.name, modloader.version),
-            icon: Some(icon),
-            name: info.name.clone(),
-            profile_type: "custom".into(),
-            extra: Default::default(),
-        }
-    };
+    Ok(())
+}
 
-    let new_profile = {
-        let mut new = launcher_profile.clone();
+/// Package installer app. Prints install plan and exits without touching disk if dry_run is true.
+/// Overrides in the package are not included in the plan.
+pub async fn run_package(package_path: PathBuf, config: &AppConfig, dry_run: bool) -> Result<(), AppError> {
+    let mut installer = PackageInstaller::new(ZipArchive::new(BufReader::new(
+        std::fs::File::open(package_path)?,
+    ))?);
 
-        new.profiles
-            .insert(format!("modpack-{}", info.id), game_profile);
+    let manifest = installer.manifest()?;
 
-        new
-    };
+    // Select minecraft dir
+    let (data_path, launcher_profile) = ask_minecraft_dir()?;
 
-    fs::write(
-        data_path.join("launcher_profiles.json"),
-        serde_json::to_string_pretty(&new_profile)?,
-    )
-    .await?;
+    console::Term::stdout().clear_screen().unwrap_or_default();
+
+    // Print package info
+    print_manifest_info(&manifest);
+
+    let modloader = manifest.minecraft.primary_modloader().ok_or(AppError::InvalidPack)?;
+    let (modloader_name, modloader_version) = modloader.id.split_once('-').ok_or(AppError::InvalidPack)?;
+
+    let install_location = ask_install_location(data_path.join("modpacks").join(&manifest.name))?;
+
+    println!(
+        "install location: {}",
+        console::style(install_location.to_string_lossy()).yellow()
+    );
+
+    if dry_run {
+        println!("{}", console::style("Resolving pack files...").yellow());
+
+        let files = install_files(&resolve_files(&config.curseforge_api()?, manifest.files, config.fetch_concurrency).await?)?;
+        print_file_plan(&FilePlan::check(&files, &install_location)?);
+
+        return Ok(());
+    }
+
+    let mut confirm = Confirm::new();
+    confirm.with_prompt("Install?");
+    if !confirm.interact()? {
+        return Err(AppError::Cancelled);
+    }
+
+    println!("{}", console::style("Extracting overrides and resolving pack files...").yellow());
+
+    let previous = InstallReceipt::load(&install_location).map(|receipt| receipt.files).unwrap_or_default();
+    let (files, extracted) = installer
+        .install(install_location.clone(), &previous, &config.curseforge_api()?, config.fetch_concurrency)
+        .await?;
+    let files = install_files(&files)?;
+
+    let report = install_pack_files(files, &install_location, config, true).await?;
+
+    let mut receipt = InstallReceipt::new_package(
+        manifest.name.clone(),
+        manifest.version.clone(),
+        package_targets(&manifest.minecraft.version, modloader_name, modloader_version),
+        &report,
+    );
+    receipt.files.extend(extracted);
+    receipt.save(&install_location)?;
+
+    confirm_incomplete_install(&report, true)?;
+
+    println!("{}", console::style("Installing pack profile...").yellow());
+
+    print_modloader_notice(&manifest.minecraft.version, modloader_name, modloader_version);
+
+    let game_profile = create_game_profile(
+        &manifest.name,
+        "Furnace".into(),
+        &install_location,
+        &manifest.minecraft.version,
+        modloader_name,
+        modloader_version,
+    );
+
+    install_game_profile(&data_path, &launcher_profile, format!("modpack-{}", manifest.name), game_profile).await?;
+
+    if !report.is_complete() {
+        return Err(AppError::IncompleteInstall(report.failed().count()));
+    }
 
     println!("{}",console::style("Finished installing modpack. Adjust game profile manually for proper launch.").green());
 
@@ -207,9 +315,9 @@ fn ask_minecraft_dir() -> Result<(PathBuf, LauncherProfile), AppError> {
 }
 
 /// Ask modpack to search and return selected pack
-async fn ask_pack_to_install() -> Result<ModPackVariant, AppError> {
+async fn ask_pack_to_install(api: &ModPackAPI, fetch_concurrency: usize) -> Result<ModPackVariant, AppError> {
     let ask = || async {
-        let pack_list = create_list_from_result(search_pack().await?).await?;
+        let pack_list = create_list_from_result(api, search_pack(api).await?, fetch_concurrency).await?;
         let pack_selector = ModpackSelect::new(pack_list);
 
         Ok::<Option<ModPackVariant>, AppError>(
@@ -219,7 +327,7 @@ async fn ask_pack_to_install() -> Result<ModPackVariant, AppError> {
 
     let mut selected = ask().await?;
 
-    while let None = selected {
+    while selected.is_none() {
         let sel = ask().await?;
 
         if sel.is_some() {
@@ -233,13 +341,36 @@ async fn ask_pack_to_install() -> Result<ModPackVariant, AppError> {
 }
 
 /// Ask search term to user and return search result
-async fn search_pack() -> Result<SearchResult, AppError> {
+async fn search_pack(api: &ModPackAPI) -> Result<SearchResult, AppError> {
     let mut input = Input::<String>::new();
     input.with_prompt("Modpack name to install\n");
 
     let keyword = input.interact_text()?;
 
-    Ok::<SearchResult, AppError>(ModPackAPI::search(&keyword, 50).await?)
+    Ok::<SearchResult, AppError>(api.search(&keyword, 50).await?)
+}
+
+/// Print package manifest information to terminal
+fn print_manifest_info(manifest: &PackManifest) {
+    println!(
+        "{}",
+        console::style(format!(
+            "{} {} by {}",
+            &console::style(&manifest.name).green(),
+            manifest.version,
+            manifest.author
+        ))
+        .bold()
+    );
+
+    if !manifest.description.is_empty() {
+        println!("{}", manifest.description);
+    }
+
+    println!(
+        "minecraft: {}",
+        console::style(&manifest.minecraft.version).yellow()
+    );
 }
 
 /// Print modpack information to terminal
@@ -270,7 +401,7 @@ fn print_pack_info(info: &ModPack) {
 }
 
 /// Ask pack version to install and fetch manifest
-async fn ask_pack_version(pack: &ModPackVariant) -> Result<Option<PackVersionData>, AppError> {
+async fn ask_pack_version(api: &ModPackAPI, pack: &ModPackVariant) -> Result<Option<PackVersionData>, AppError> {
     let ver_selector = PackVersionSelect::new(pack.info().versions.clone());
     let ver = ver_selector.select(Some("Select version to install using arrow key"))?;
 
@@ -281,14 +412,17 @@ async fn ask_pack_version(pack: &ModPackVariant) -> Result<Option<PackVersionDat
 
     println!("{}", console::style("Preparing version data...").yellow());
 
+    Ok(Some(fetch_version_data(api, pack, ver.id).await?))
+}
+
+/// Fetch pack version data using version id
+async fn fetch_version_data(api: &ModPackAPI, pack: &ModPackVariant, version_id: u32) -> Result<PackVersionData, AppError> {
     let version_data = match pack {
-        ModPackVariant::ModPack(info) => ModPackAPI::modpack_version_data(info.id, ver.id).await?,
-        ModPackVariant::CurseForge(info) => {
-            ModPackAPI::curseforge_version_data(info.id, ver.id).await?
-        }
+        ModPackVariant::ModPack(info) => api.modpack_version_data(info.id, version_id).await?,
+        ModPackVariant::CurseForge(info) => api.curseforge_version_data(info.id, version_id).await?,
     };
 
-    Ok(Some(version_data))
+    Ok(version_data)
 }
 
 /// Ask install directory
@@ -301,7 +435,7 @@ fn ask_install_location(default_path: PathBuf) -> Result<PathBuf, AppError> {
 }
 
 /// Print install information
-fn print_install_info(pack: &ModPack, version: &PackVersionData, location: &PathBuf) {
+fn print_install_info(pack: &ModPack, version: &PackVersionData, location: &Path) {
     println!("name: {}", console::style(&pack.name).yellow());
     println!("type: {}", console::style(&version.version_type).yellow());
     println!("version: {}", console::style(&version.name).yellow());
@@ -323,50 +457,371 @@ fn print_install_info(pack: &ModPack, version: &PackVersionData, location: &Path
     );
 }
 
-// Get files to install from pack version
-fn install_files(ver: &PackVersionData) -> Result<Vec<PackFile>, AppError> {
-    let required = ver
-        .files
-        .clone()
-        .into_iter()
-        .filter(|file| !file.info.optional)
-        .collect::<Vec<PackFile>>();
+/// Print files to download, valid files and extra files of install plan
+pub fn print_file_plan(plan: &FilePlan) {
+    let file_path = |file: &PackFile| Path::new(&file.info.path).join(&file.info.name);
 
-    if required.len() != ver.files.len() {
+    for planned in plan.with_action(FileAction::Download) {
+        println!(
+            "{} {} ({})",
+            console::style("download").yellow(),
+            file_path(&planned.file).to_string_lossy(),
+            planned.file.info.size.map_or_else(|| "unknown".into(), |size| size.file_size(file_size_opts::BINARY).unwrap())
+        );
+    }
+
+    for planned in plan.with_action(FileAction::Valid) {
+        println!("{} {}", console::style("valid").green(), file_path(&planned.file).to_string_lossy());
+    }
+
+    for planned in plan.with_action(FileAction::Reject) {
+        println!("{} {}", console::style("unsafe").red(), file_path(&planned.file).to_string_lossy());
+    }
+
+    for path in &plan.extra_files {
+        println!("{} {}", console::style("extra").cyan(), path.to_string_lossy());
+    }
+
+    println!(
+        "download: {} files, {}",
+        console::style(plan.with_action(FileAction::Download).count()).yellow(),
+        console::style(plan.download_size.file_size(file_size_opts::BINARY).unwrap()).yellow()
+    );
+}
+
+/// Print added, changed and removed files of update diff
+pub fn print_file_diff(diff: &FileDiff) {
+    let file_path = |path: &str, name: &str| Path::new(path).join(name);
+
+    for file in &diff.added {
+        println!("{} {}", console::style("add").green(), file_path(&file.info.path, &file.info.name).to_string_lossy());
+    }
+
+    for file in &diff.changed {
+        println!("{} {}", console::style("change").yellow(), file_path(&file.info.path, &file.info.name).to_string_lossy());
+    }
+
+    for file in &diff.removed {
+        println!("{} {}", console::style("remove").red(), file_path(&file.path, &file.name).to_string_lossy());
+    }
+
+    println!(
+        "{} added, {} changed, {} removed, {} unchanged",
+        console::style(diff.added.len()).green(),
+        console::style(diff.changed.len()).yellow(),
+        console::style(diff.removed.len()).red(),
+        diff.unchanged.len()
+    );
+}
+
+// Get files to install from pack files
+fn install_files(files: &[PackFile]) -> Result<Vec<PackFile>, AppError> {
+    if files.iter().any(|file| file.info.optional) {
         let mut optional_ask = Confirm::new();
         optional_ask.with_prompt("Install optional resources?");
-        if !optional_ask.interact()? {
-            Ok(required)
-        } else {
-            Ok(ver.files.clone())
-        }
+
+        Ok(select_files(files, optional_ask.interact()?))
     } else {
-        Ok(required)
+        Ok(files.to_vec())
     }
 }
 
-/// Create base64 modpack profile icon
-async fn create_profile_icon(pack: &ModPack) -> String {
-    let icon_list = pack
-        .arts
-        .clone()
-        .into_iter()
-        .filter(|art| art.art_type == PackArtType::Square)
-        .collect::<Vec<PackArt>>();
+/// Select files to install. Optional files are excluded unless optional is true.
+fn select_files(files: &[PackFile], optional: bool) -> Vec<PackFile> {
+    files
+        .iter()
+        .filter(|file| optional || !file.info.optional)
+        .cloned()
+        .collect()
+}
 
-    if icon_list.len() < 1 {
-        "Furnace".into()
-    } else {
-        let icon = &icon_list[0];
+/// Read launcher profile of minecraft data directory
+fn load_launcher_profile(data_path: &Path) -> Result<LauncherProfile, AppError> {
+    let file = std::fs::File::open(data_path.join(LAUNCHER_PROFILE_FILE))?;
+
+    Ok(serde_json::from_reader(BufReader::new(file))?)
+}
+
+/// Install pack files and returns install report
+async fn install_pack_files(
+    files: Vec<PackFile>,
+    install_location: &Path,
+    config: &AppConfig,
+    show_progress: bool,
+) -> Result<InstallReport, AppError> {
+    update_pack_files(files, install_location, config, show_progress, HashMap::new()).await
+}
+
+/// Download pack files without extracting overrides and returns install report.
+/// Download location is not registered to content store.
+async fn download_pack_files(
+    files: Vec<PackFile>,
+    download_location: &Path,
+    config: &AppConfig,
+    show_progress: bool,
+) -> Result<InstallReport, AppError> {
+    let source = HttpSource::new_limited(config.http_client()?, config.bandwidth_limiter());
+
+    run_install_task(Arc::new(source), files, download_location, None, config, show_progress).await
+}
+
+/// Install changed pack files of update and returns install report.
+/// Modified files extracted from previous overrides are handled by extract_actions.
+async fn update_pack_files(
+    files: Vec<PackFile>,
+    install_location: &Path,
+    config: &AppConfig,
+    show_progress: bool,
+    extract_actions: HashMap<PathBuf, ConfigAction>,
+) -> Result<InstallReport, AppError> {
+    let source = HttpSource::new_limited(config.http_client()?, config.bandwidth_limiter());
+
+    install_source_files(Arc::new(source), files, install_location, config, show_progress, Some(extract_actions)).await
+}
+
+/// Install pack files extracted from bundle and returns install report
+async fn install_bundle_files(
+    bundle: &Path,
+    files: Vec<PackFile>,
+    install_location: &Path,
+    config: &AppConfig,
+    show_progress: bool,
+) -> Result<InstallReport, AppError> {
+    let source = BundleSource::new(bundle.to_path_buf());
+
+    install_source_files(Arc::new(source), files, install_location, config, show_progress, Some(HashMap::new())).await
+}
+
+/// Install pack files fetched from source and returns install report
+async fn install_source_files(
+    source: Arc<dyn FileSource>,
+    files: Vec<PackFile>,
+    install_location: &Path,
+    config: &AppConfig,
+    show_progress: bool,
+    extract_actions: Option<HashMap<PathBuf, ConfigAction>>,
+) -> Result<InstallReport, AppError> {
+    let report = run_install_task(source, files, install_location, extract_actions, config, show_progress).await?;
+
+    // Keep files of this location on garbage collection
+    if let Some(store) = config.content_store() {
+        store.register(install_location)?;
+    }
+
+    Ok(report)
+}
+
+/// Run install task until every file is done and returns install report
+async fn run_install_task(
+    source: Arc<dyn FileSource>,
+    files: Vec<PackFile>,
+    install_location: &Path,
+    extract_actions: Option<HashMap<PathBuf, ConfigAction>>,
+    config: &AppConfig,
+    show_progress: bool,
+) -> Result<InstallReport, AppError> {
+    let multi = MultiProgress::new();
+
+    let install_task_handle = spawn_install_task(
+        source,
+        files,
+        install_location.to_path_buf(),
+        extract_actions,
+        config,
+        Some(&multi).filter(|_| show_progress),
+    );
+
+    if show_progress {
+        multi.join()?;
+    }
+    install_task_handle.await.map_err(TaskError::from)?
+}
+
+/// Install launcher profile of installed pack version
+async fn install_pack_profile(
+    api: &ModPackAPI,
+    data_path: &Path,
+    launcher_profile: &LauncherProfile,
+    info: &ModPack,
+    ver: &PackVersionData,
+    install_location: &Path,
+) -> Result<(), AppError> {
+    let icon = profile_icon(fetch_pack_icon(api, info).await.as_deref());
+
+    install_pack_profile_icon(data_path, launcher_profile, info, ver, install_location, icon).await
+}
+
+/// Install launcher profile of installed pack version with profile icon
+async fn install_pack_profile_icon(
+    data_path: &Path,
+    launcher_profile: &LauncherProfile,
+    info: &ModPack,
+    ver: &PackVersionData,
+    install_location: &Path,
+    icon: String,
+) -> Result<(), AppError> {
+    let (game, modloader) = launch_targets(ver)?;
+
+    let game_profile = create_game_profile(
+        &info.name,
+        icon,
+        install_location,
+        &game.version,
+        &modloader.name,
+        &modloader.version,
+    );
+
+    install_game_profile(data_path, launcher_profile, format!("modpack-{}", info.id), game_profile).await
+}
+
+/// Create launch targets of overrides package
+fn package_targets(game_version: &str, modloader_name: &str, modloader_version: &str) -> Vec<PackTarget> {
+    vec![
+        PackTarget {
+            id: 0,
+            name: "minecraft".into(),
+            target_type: "game".into(),
+            updated: 0,
+            version: game_version.into(),
+        },
+        PackTarget {
+            id: 0,
+            name: modloader_name.into(),
+            target_type: "modloader".into(),
+            updated: 0,
+            version: modloader_version.into(),
+        },
+    ]
+}
+
+/// Find (game, modloader) launch targets of pack version
+fn launch_targets(ver: &PackVersionData) -> Result<(&PackTarget, &PackTarget), AppError> {
+    let game = ver
+        .targets
+        .iter()
+        .find(|target| target.target_type == "game")
+        .ok_or(AppError::InvalidPack)?;
+
+    let modloader = ver
+        .targets
+        .iter()
+        .find(|target| target.target_type == "modloader")
+        .ok_or(AppError::InvalidPack)?;
+
+    Ok((game, modloader))
+}
+
+/// Print where to get modloader
+fn print_modloader_notice(game_version: &str, modloader_name: &str, modloader_version: &str) {
+    println!(
+        "{} {}",
+        console::style(format!("Install {} {} {} from", modloader_name, game_version, modloader_version)).yellow(),
+        console::style(format!("https://files.minecraftforge.net/net/minecraftforge/forge/index_{}.html", game_version)).yellow().bold()
+    );
+}
+
+/// Check if required files failed to install and ask to continue.
+/// Fails without asking if it's not interactive.
+fn confirm_incomplete_install(report: &InstallReport, interactive: bool) -> Result<(), AppError> {
+    let failed_required = report.failed_required().count();
+
+    if failed_required > 0 {
+        if !interactive {
+            return Err(AppError::IncompleteInstall(report.failed().count()));
+        }
+
+        let mut confirm = Confirm::new();
+        confirm.with_prompt(format!(
+            "{} required files failed to install. Install launcher profile anyway?",
+            failed_required
+        ));
+        confirm.default(false);
+
+        if !confirm.interact()? {
+            return Err(AppError::IncompleteInstall(report.failed().count()));
+        }
+    }
+
+    Ok(())
+}
+
+/// Create game launch profile of installed pack
+fn create_game_profile(
+    name: &str,
+    icon: String,
+    location: &Path,
+    game_version: &str,
+    modloader_name: &str,
+    modloader_version: &str,
+) -> GameLaunchProfile {
+    let time: String = Utc::now().to_string();
+
+    GameLaunchProfile {
+        created: Some(time.clone()),
+        last_used: Some(time),
+        game_dir: Some(location.to_string_lossy().into()),
+        java_args: None,
+        last_version_id: format!("Change this to valid {} {}-{} version", game_version, modloader_name, modloader_version),
+        icon: Some(icon),
+        name: name.into(),
+        profile_type: "custom".into(),
+        extra: Default::default(),
+    }
+}
+
+/// Add game profile to launcher profile and save it
+async fn install_game_profile(
+    data_path: &Path,
+    launcher_profile: &LauncherProfile,
+    key: String,
+    game_profile: GameLaunchProfile,
+) -> Result<(), AppError> {
+    let new_profile = {
+        let mut new = launcher_profile.clone();
+
+        new.profiles.insert(key, game_profile);
+
+        new
+    };
+
+    fs::write(
+        data_path.join(LAUNCHER_PROFILE_FILE),
+        serde_json::to_string_pretty(&new_profile)?,
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Remove game profile from launcher profile. Returns false if the profile doesn't exist.
+async fn remove_game_profile(data_path: &Path, launcher_profile: &LauncherProfile, key: &str) -> Result<bool, AppError> {
+    if !launcher_profile.profiles.contains_key(key) {
+        return Ok(false);
+    }
+
+    let mut new_profile = launcher_profile.clone();
+    new_profile.profiles.remove(key);
+
+    fs::write(
+        data_path.join(LAUNCHER_PROFILE_FILE),
+        serde_json::to_string_pretty(&new_profile)?,
+    )
+    .await?;
 
-        (|| async {
-            let res = reqwest::get(&icon.info.url).await?;
+    Ok(true)
+}
+
+/// Fetch square modpack icon image. Returns None if the pack has no icon or fetch failed.
+async fn fetch_pack_icon(api: &ModPackAPI, pack: &ModPack) -> Option<Bytes> {
+    let icon = pack.arts.iter().find(|art| art.art_type == PackArtType::Square)?;
 
-            let base64 = base64::encode(res.bytes().await?.to_vec());
+    api.resource(&icon.info.url).await.ok()
+}
 
-            Ok::<String, reqwest::Error>(format!("data:image/png;base64,{}", base64))
-        })()
-        .await
-        .unwrap_or("Furnace".into())
+/// Create base64 modpack profile icon from icon image
+fn profile_icon(icon: Option<&[u8]>) -> String {
+    match icon {
+        Some(icon) => format!("data:image/png;base64,{}", base64::encode(icon)),
+        None => "Furnace".into(),
     }
 }
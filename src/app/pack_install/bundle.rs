@@ -0,0 +1,237 @@
+/*
+ * Created on Sat May 29 2021
+ *
+ * Copyright (c) storycraft. Licensed under the GNU General Public License v3.
+ */
+
+//! Offline install bundle containing pack files and version data
+
+use std::{
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::{Component, Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use zip::{result::ZipError, write::FileOptions, CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::{
+    api::modpack::{
+        data::{PackFileInfo, PackVersionData},
+        info::ModPack,
+    },
+    app::pack_select::ModPackVariant,
+};
+
+use super::receipt::PackSource;
+
+/// Manifest entry name in bundle
+pub const BUNDLE_MANIFEST: &str = "bundle.json";
+
+/// Pack icon entry name in bundle
+pub const BUNDLE_ICON: &str = "icon.png";
+
+/// Pack file directory in bundle
+pub const BUNDLE_FILES_DIR: &str = "files";
+
+/// Bundle manifest
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundleManifest {
+    /// Pack source
+    pub source: PackSource,
+
+    /// Pack manifest
+    pub pack: ModPack,
+
+    /// Version data. Only contains files in bundle.
+    pub version: PackVersionData,
+}
+
+impl BundleManifest {
+    /// Pack variant of bundled pack
+    pub fn pack_variant(&self) -> ModPackVariant {
+        match self.source {
+            PackSource::CurseForge => ModPackVariant::CurseForge(self.pack.clone()),
+            _ => ModPackVariant::ModPack(self.pack.clone()),
+        }
+    }
+}
+
+/// Bundle entry name of pack file. Returns None if the file path is unsafe.
+pub fn bundle_entry_name(file: &PackFileInfo) -> Option<String> {
+    let install_path = file.install_path()?;
+
+    let mut name = String::from(BUNDLE_FILES_DIR);
+    for component in install_path.components() {
+        if let Component::Normal(part) = component {
+            name.push('/');
+            name.push_str(&part.to_string_lossy());
+        }
+    }
+
+    Some(name)
+}
+
+/// Write bundle of files installed in location to path. Blocks current thread.
+/// Bundle is written to temporary file first so incomplete bundle is never visible.
+pub fn write_bundle(
+    path: &Path,
+    manifest: &BundleManifest,
+    icon: Option<&[u8]>,
+    location: &Path,
+) -> Result<(), ZipError> {
+    let part_path = PathBuf::from(format!("{}.part", path.to_string_lossy()));
+
+    let mut writer = ZipWriter::new(BufWriter::new(File::create(&part_path)?));
+
+    writer.start_file(BUNDLE_MANIFEST, FileOptions::default())?;
+    serde_json::to_writer_pretty(&mut writer, manifest).map_err(io::Error::from)?;
+
+    // Pack files are mostly compressed already
+    let stored = FileOptions::default().compression_method(CompressionMethod::Stored);
+
+    if let Some(icon) = icon {
+        writer.start_file(BUNDLE_ICON, stored)?;
+        writer.write_all(icon)?;
+    }
+
+    for file in &manifest.version.files {
+        let (name, install_path) = match (bundle_entry_name(&file.info), file.info.install_path()) {
+            (Some(name), Some(install_path)) => (name, install_path),
+            _ => continue,
+        };
+
+        writer.start_file(name, stored)?;
+        io::copy(&mut BufReader::new(File::open(location.join(install_path))?), &mut writer)?;
+    }
+
+    writer.finish()?.flush()?;
+    fs::rename(part_path, path)?;
+
+    Ok(())
+}
+
+/// Read bundle manifest and icon. Blocks current thread.
+pub fn read_bundle(path: &Path) -> Result<(BundleManifest, Option<Vec<u8>>), ZipError> {
+    let mut archive = ZipArchive::new(BufReader::new(File::open(path)?))?;
+
+    let manifest = serde_json::from_reader(archive.by_name(BUNDLE_MANIFEST)?).map_err(io::Error::from)?;
+
+    let icon = match archive.by_name(BUNDLE_ICON) {
+        Ok(mut entry) => {
+            let mut icon = Vec::new();
+            entry.read_to_end(&mut icon)?;
+
+            Some(icon)
+        }
+
+        Err(ZipError::FileNotFound) => None,
+
+        Err(err) => return Err(err),
+    };
+
+    Ok((manifest, icon))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use futures::StreamExt;
+
+    use crate::api::modpack::data::{FileVersion, PackVersionData};
+
+    use super::{
+        super::{
+            receipt::PackSource,
+            source::{BundleSource, FileSource},
+        },
+        read_bundle, write_bundle, BundleManifest,
+    };
+
+    const PACK: &str = r#"{
+        "id": 1, "name": "pack", "synopsis": null, "description": "desc", "featured": false,
+        "installs": 10, "plays": 20, "refreshed": 1, "updated": 2, "type": "Release", "notification": "",
+        "tags": [{ "id": 1, "name": "tag" }],
+        "rating": {
+            "id": 1, "age": 0, "alcoholdrugs": false, "configured": true, "frightening": false, "gambling": false,
+            "language": false, "nuditysexual": false, "sterotypeshate": false, "verified": false, "violence": false
+        },
+        "versions": [
+            { "id": 10, "name": "1.0", "updated": 3, "specs": "", "type": "Release" },
+            { "id": 11, "name": "1.1", "updated": 4, "specs": { "id": 1, "minimum": 4096, "recommended": 8192 }, "type": "Beta" }
+        ],
+        "art": [{
+            "type": "square", "id": 1, "compressed": false, "width": 64, "height": 64,
+            "sha1": "", "size": 0, "updated": 1, "url": "https://example.com/icon.png"
+        }],
+        "authors": [{ "id": 1, "name": "author", "type": "team", "website": "", "updated": 1 }],
+        "links": []
+    }"#;
+
+    const VERSION: &str = r#"{
+        "id": 10, "name": "1.0", "installs": 1, "plays": 2, "type": "Release", "notification": "", "specs": "",
+        "refreshed": 1, "updated": 2, "links": [], "parent": 1,
+        "targets": [{ "id": 1, "name": "forge", "type": "modloader", "updated": 1, "version": "36.1.0" }],
+        "files": [
+            {
+                "type": "mod", "id": 1, "name": "a.jar", "optional": false, "path": "./mods/", "clientonly": false,
+                "serveronly": false, "sha1": "86f7e437faa5a7fce15d1ddcb9eaeaea377667b8", "size": 1, "updated": 1,
+                "url": "https://example.com/a.jar", "version": 1
+            },
+            {
+                "type": "config", "id": 2, "name": "b.cfg", "optional": true, "path": "config\\sub", "clientonly": false,
+                "serveronly": false, "size": 0, "updated": 1, "url": "https://example.com/b.cfg", "version": "1.0"
+            }
+        ]
+    }"#;
+
+    #[tokio::test]
+    async fn bundle_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let staging = dir.path().join("staging");
+        let path = dir.path().join("pack.zip");
+
+        let manifest = BundleManifest {
+            source: PackSource::ModPacks,
+            pack: serde_json::from_str(PACK).unwrap(),
+            version: serde_json::from_str::<PackVersionData>(VERSION).unwrap(),
+        };
+
+        fs::create_dir_all(staging.join("mods")).unwrap();
+        fs::create_dir_all(staging.join("config").join("sub")).unwrap();
+        fs::write(staging.join("mods").join("a.jar"), "a").unwrap();
+        fs::write(staging.join("config").join("sub").join("b.cfg"), "config").unwrap();
+
+        write_bundle(&path, &manifest, Some(b"icon"), &staging).unwrap();
+        assert!(!dir.path().join("pack.zip.part").exists());
+
+        let (read, icon) = read_bundle(&path).unwrap();
+
+        assert_eq!(icon.as_deref(), Some(&b"icon"[..]));
+        assert_eq!(serde_json::to_value(&read).unwrap(), serde_json::to_value(&manifest).unwrap());
+
+        assert!(read.pack.versions[0].specs.is_none());
+        assert_eq!(read.pack.versions[1].specs.as_ref().map(|specs| specs.minimum), Some(4096));
+        assert!(read.version.specs.is_none());
+        assert_eq!(read.version.files[0].info.size, Some(1));
+        assert_eq!(read.version.files[1].info.size, None);
+        assert!(read.version.files[1].info.sha1.is_empty());
+        assert!(matches!(read.version.files[1].info.version, FileVersion::Semantic(_)));
+
+        let source = BundleSource::new(path);
+
+        for (file, content) in read.version.files.iter().zip(&["a", "config"]) {
+            let mut fetched = source.fetch(file, 0).await.unwrap();
+            assert_eq!(fetched.offset, 0);
+
+            let mut data = Vec::new();
+            while let Some(chunk) = fetched.stream.next().await {
+                data.extend_from_slice(&chunk.unwrap());
+            }
+
+            assert_eq!(data, content.as_bytes());
+        }
+    }
+}
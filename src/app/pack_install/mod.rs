@@ -6,3 +6,12 @@
 
 pub mod web;
 pub mod package;
+pub mod report;
+pub mod store;
+pub mod bundle;
+pub mod source;
+pub mod plan;
+pub mod receipt;
+pub mod update;
+pub mod uninstall;
+pub mod verify;
@@ -5,14 +5,35 @@
  */
 
 use std::{
-    fs::File,
+    collections::HashMap,
+    error::Error,
+    fmt::{self, Display, Formatter},
+    fs::{self, File},
     io::{self, BufReader, BufWriter, Read, Seek},
-    path::PathBuf,
+    path::{Path, PathBuf},
 };
 
+use futures::{stream, StreamExt};
+use indicatif::ProgressBar;
 use zip::{result::ZipError, ZipArchive};
 
-use crate::api::overrides::manifest::PackManifest;
+use crate::{
+    api::{
+        curseforge::CurseForgeAPI,
+        modpack::{
+            data::{PackFile, PackFileType},
+            ModPackApiError,
+        },
+        overrides::manifest::{self, PackManifest},
+    },
+    util::{
+        file::check_file,
+        hash::copy_hashed,
+        path::normalize_relative,
+    },
+};
+
+use super::{receipt::ReceiptFile, update::ConfigAction};
 
 // Package install errors
 #[derive(Debug)]
@@ -20,6 +41,18 @@ pub enum PackageInstallError {
     Package(ZipError),
     Io(io::Error),
     Manifest(serde_json::Error),
+
+    /// Manifest file cannot be resolved. (project id, file id, error)
+    Resolve(u32, u32, ModPackApiError),
+
+    /// Package entry path is absolute or escapes install location. (entry name)
+    UnsafePath(String),
+
+    /// Manifest file author disallowed third party downloads. (project id, file id)
+    Restricted(u32, u32),
+
+    /// Curseforge api key is not configured
+    MissingApiKey,
 }
 
 impl From<ZipError> for PackageInstallError {
@@ -40,6 +73,37 @@ impl From<serde_json::Error> for PackageInstallError {
     }
 }
 
+impl Display for PackageInstallError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match &self {
+            PackageInstallError::Package(err) => err.fmt(f),
+            PackageInstallError::Io(err) => err.fmt(f),
+            PackageInstallError::Manifest(err) => err.fmt(f),
+            PackageInstallError::Resolve(project_id, file_id, err) => write!(
+                f,
+                "Cannot resolve file {} of project {}. err: {}",
+                file_id, project_id, err
+            ),
+            PackageInstallError::UnsafePath(name) => write!(
+                f,
+                "Package entry {} points outside of install location",
+                name
+            ),
+            PackageInstallError::Restricted(project_id, file_id) => write!(
+                f,
+                "File {} of project {} cannot be downloaded by third party apps",
+                file_id, project_id
+            ),
+            PackageInstallError::MissingApiKey => write!(
+                f,
+                "Curseforge api key is required to resolve package files. Set curseforgeApiKey in config"
+            ),
+        }
+    }
+}
+
+impl Error for PackageInstallError {}
+
 /// Pack installer that uses package zip (known as overrides.zip)
 pub struct PackageInstaller<T: Read + Seek> {
     /// Package reader
@@ -51,44 +115,320 @@ impl<T: Read + Seek> PackageInstaller<T> {
         Self { archive }
     }
 
-    /// Read package and install to location
-    pub async fn install(mut self, location: PathBuf) -> Result<(), PackageInstallError> {
-        let manifest = {
-            let file = self.archive.by_name("manifest.json")?;
+    /// Read package manifest
+    pub fn manifest(&mut self) -> Result<PackManifest, PackageInstallError> {
+        let file = self.archive.by_name("manifest.json")?;
+
+        Ok(serde_json::from_reader(file)?)
+    }
+
+    /// Extract package overrides to location and report each entry to progress.
+    /// If package doesn't have manifest, every entries are treated as overrides.
+    /// Files previously extracted and modified since are handled by action of their install path and kept if not listed.
+    /// Returns every extracted or kept file.
+    pub fn extract_overrides(
+        &mut self,
+        location: &Path,
+        previous: &[ReceiptFile],
+        actions: &HashMap<PathBuf, ConfigAction>,
+        progress: &ProgressBar,
+    ) -> Result<Vec<ReceiptFile>, PackageInstallError> {
+        let override_dir = match self.manifest() {
+            Ok(manifest) => match manifest.overrides {
+                Some(override_dir) => override_dir,
+                None => return Ok(Vec::new()),
+            },
+
+            Err(PackageInstallError::Package(ZipError::FileNotFound)) => String::new(),
+
+            Err(err) => return Err(err),
+        };
+
+        let override_dir_path = normalize_relative(Path::new(&override_dir))
+            .ok_or_else(|| PackageInstallError::UnsafePath(override_dir.clone()))?;
+        let override_list: Vec<String> = self
+            .archive
+            .file_names()
+            .filter(|path| Path::new(path).starts_with(&override_dir_path))
+            .map(|path| path.into())
+            .collect();
+
+        let previous = previous
+            .iter()
+            .filter(|file| file.extracted)
+            .filter_map(|file| Some((file.install_path()?, file)))
+            .collect::<HashMap<_, _>>();
+
+        let mut extracted = Vec::new();
+
+        progress.set_length(override_list.len() as u64);
+
+        for override_path in &override_list {
+            progress.set_message(override_path.clone());
 
-            Ok::<PackManifest, PackageInstallError>(serde_json::from_reader(file)?)
-        }?;
+            let mut entry = self.archive.by_name(override_path)?;
 
-        match &manifest.overrides {
-            Some(override_dir) => {
-                let override_dir_path = PathBuf::from(&override_dir);
-                let override_list: Vec<String> = self
-                    .archive
-                    .file_names()
-                    .filter(|path| path.starts_with(override_dir)) // unwrap
-                    .map(|path| path.clone().into())
-                    .collect();
+            let relative_path = {
+                let path: PathBuf = override_path.into();
 
-                for override_path in &override_list {
-                    let out_path = {
-                        let path: PathBuf = override_path.into();
+                normalize_relative(&path.iter().skip(override_dir_path.iter().count()).collect::<PathBuf>())
+                    .ok_or_else(|| PackageInstallError::UnsafePath(override_path.clone()))?
+            };
+            let out_path = location.join(&relative_path);
 
-                        path.into_iter().skip(override_dir_path.iter().count()).collect::<PathBuf>() // todo
-                    };
+            if entry.is_dir() {
+                fs::create_dir_all(out_path)?;
+            } else {
+                let action = match previous.get(&relative_path) {
+                    Some(file) if out_path.is_file() && !check_file(&out_path, file.size, &file.sha1) => {
+                        Some((file, actions.get(&relative_path).copied().unwrap_or(ConfigAction::Keep)))
+                    }
 
-                    let mut reader = BufReader::new(self.archive.by_name(override_path)?);
-                    let mut writer = BufWriter::new(File::create(location.join(out_path))?);
+                    _ => None,
+                };
 
-                    io::copy(&mut reader, &mut writer)?;
+                match action {
+                    Some((file, ConfigAction::Keep)) => extracted.push((*file).clone()),
+
+                    Some((file, ConfigAction::Backup)) => {
+                        extracted.push((*file).clone());
+
+                        let mut backup_path = relative_path.clone().into_os_string();
+                        backup_path.push(".new");
+
+                        extracted.push(extract_entry(&mut entry, location, Path::new(&backup_path))?);
+                    }
+
+                    Some((_, ConfigAction::Overwrite)) | None => {
+                        extracted.push(extract_entry(&mut entry, location, &relative_path)?);
+                    }
                 }
             }
-            None => {}
-        };
 
-        for file in &manifest.files {
-            // TODO
+            progress.inc(1);
+        }
+
+        Ok(extracted)
+    }
+
+    /// Read package, install overrides to location and returns resolved manifest files to download with extracted files.
+    /// Files are resolved before extracting so nothing is written if resolving fails.
+    pub async fn install(
+        mut self,
+        location: PathBuf,
+        previous: &[ReceiptFile],
+        api: &CurseForgeAPI,
+        concurrency: usize,
+    ) -> Result<(Vec<PackFile>, Vec<ReceiptFile>), PackageInstallError> {
+        let manifest = self.manifest()?;
+
+        let files = resolve_files(api, manifest.files, concurrency).await?;
+        let extracted = self.extract_overrides(&location, previous, &HashMap::new(), &ProgressBar::hidden())?;
+
+        Ok((files, extracted))
+    }
+}
+
+/// Write package entry to relative path under location and returns its receipt entry
+fn extract_entry(entry: &mut impl Read, location: &Path, relative_path: &Path) -> Result<ReceiptFile, PackageInstallError> {
+    let out_path = location.join(relative_path);
+
+    if let Some(parent) = out_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut reader = BufReader::new(entry);
+    let mut writer = BufWriter::new(File::create(out_path)?);
+
+    let (size, sha1) = copy_hashed(&mut reader, &mut writer)?;
+
+    Ok(extracted_file(relative_path, sha1, size))
+}
+
+/// Receipt entry of file extracted to relative path.
+/// Extracted files are treated as config files so user modifications are preserved.
+fn extracted_file(relative_path: &Path, sha1: String, size: u64) -> ReceiptFile {
+    let path = relative_path
+        .parent()
+        .map(|dir| {
+            dir.iter()
+                .map(|name| name.to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/")
+        })
+        .unwrap_or_default();
+
+    ReceiptFile {
+        file_type: PackFileType::Config,
+        path,
+        name: relative_path
+            .file_name()
+            .map(|name| name.to_string_lossy().into())
+            .unwrap_or_default(),
+        sha1,
+        size: Some(size),
+        extracted: true,
+    }
+}
+
+/// Resolve manifest files into downloadable pack files with concurrency.
+/// Files with `required: Some(false)` are resolved as optional.
+pub async fn resolve_files(
+    api: &CurseForgeAPI,
+    files: Vec<manifest::PackFile>,
+    concurrency: usize,
+) -> Result<Vec<PackFile>, PackageInstallError> {
+    if !files.is_empty() && !api.has_api_key() {
+        return Err(PackageInstallError::MissingApiKey);
+    }
+
+    let resolve_stream = stream::iter(files).map(|file| async move {
+        let optional = !file.required.unwrap_or(true);
+
+        match api.addon_file(file.project_id, file.file_id).await {
+            Ok(addon_file) => addon_file
+                .to_pack_file(optional)
+                .ok_or(PackageInstallError::Restricted(file.project_id, file.file_id)),
+            Err(err) => Err(PackageInstallError::Resolve(file.project_id, file.file_id, err)),
+        }
+    });
+
+    resolve_stream
+        .buffer_unordered(concurrency.max(1))
+        .collect::<Vec<Result<PackFile, PackageInstallError>>>()
+        .await
+        .into_iter()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        fs,
+        io::{Cursor, Write},
+        path::Path,
+    };
+
+    use indicatif::ProgressBar;
+    use reqwest::Client;
+    use zip::{write::FileOptions, ZipArchive, ZipWriter};
+
+    use crate::api::curseforge::{CurseForgeAPI, CURSEFORGE_API_URL};
+
+    use super::{super::update::ConfigAction, PackageInstallError, PackageInstaller};
+
+    const MANIFEST: &str = r#"{
+        "manifestType": "minecraftModpack",
+        "manifestVersion": 1,
+        "overrides": "overrides",
+        "name": "pack",
+        "version": "1.0",
+        "author": "author",
+        "files": [{ "projectID": 1, "fileID": 2, "required": true }],
+        "minecraft": { "version": "1.16.5", "modLoaders": [{ "id": "forge-36.1.0", "primary": true }] }
+    }"#;
+
+    fn package(entries: &[(&str, &str)]) -> PackageInstaller<Cursor<Vec<u8>>> {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+
+        for (name, content) in entries {
+            writer.start_file(*name, FileOptions::default()).unwrap();
+            writer.write_all(content.as_bytes()).unwrap();
         }
 
-        Ok(())
+        PackageInstaller::new(ZipArchive::new(writer.finish().unwrap()).unwrap())
+    }
+
+    #[test]
+    fn extract_records_every_file() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let extracted = package(&[("config/a.cfg", "a"), ("b.txt", "bb")])
+            .extract_overrides(dir.path(), &[], &HashMap::new(), &ProgressBar::hidden())
+            .unwrap();
+
+        assert_eq!(extracted.len(), 2);
+        assert!(extracted.iter().all(|file| file.extracted));
+
+        let a = extracted.iter().find(|file| file.name == "a.cfg").unwrap();
+        assert_eq!(a.path, "config");
+        assert_eq!(a.size, Some(1));
+        assert_eq!(a.sha1, "86f7e437faa5a7fce15d1ddcb9eaeaea377667b8");
+        assert_eq!(a.install_path(), Some(Path::new("config").join("a.cfg")));
+
+        let b = extracted.iter().find(|file| file.name == "b.txt").unwrap();
+        assert_eq!(b.path, "");
+        assert_eq!(b.size, Some(2));
+
+        assert_eq!(fs::read_to_string(dir.path().join("config").join("a.cfg")).unwrap(), "a");
+    }
+
+    #[test]
+    fn keep_modified_files() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let previous = package(&[("a.cfg", "a"), ("b.cfg", "b")])
+            .extract_overrides(dir.path(), &[], &HashMap::new(), &ProgressBar::hidden())
+            .unwrap();
+
+        fs::write(dir.path().join("a.cfg"), "modified").unwrap();
+
+        let extracted = package(&[("a.cfg", "new a"), ("b.cfg", "new b")])
+            .extract_overrides(dir.path(), &previous, &HashMap::new(), &ProgressBar::hidden())
+            .unwrap();
+
+        assert_eq!(fs::read_to_string(dir.path().join("a.cfg")).unwrap(), "modified");
+        assert_eq!(fs::read_to_string(dir.path().join("b.cfg")).unwrap(), "new b");
+
+        // Kept file is recorded as previously extracted so it's still detected as modified
+        let a = extracted.iter().find(|file| file.name == "a.cfg").unwrap();
+        assert_eq!(a.sha1, previous.iter().find(|file| file.name == "a.cfg").unwrap().sha1);
+    }
+
+    #[test]
+    fn apply_action_to_modified_files() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let previous = package(&[("a.cfg", "a"), ("b.cfg", "b")])
+            .extract_overrides(dir.path(), &[], &HashMap::new(), &ProgressBar::hidden())
+            .unwrap();
+
+        fs::write(dir.path().join("a.cfg"), "modified").unwrap();
+        fs::write(dir.path().join("b.cfg"), "modified").unwrap();
+
+        let actions = vec![
+            (Path::new("a.cfg").to_path_buf(), ConfigAction::Overwrite),
+            (Path::new("b.cfg").to_path_buf(), ConfigAction::Backup),
+        ]
+        .into_iter()
+        .collect::<HashMap<_, _>>();
+
+        let extracted = package(&[("a.cfg", "new a"), ("b.cfg", "new b")])
+            .extract_overrides(dir.path(), &previous, &actions, &ProgressBar::hidden())
+            .unwrap();
+
+        assert_eq!(fs::read_to_string(dir.path().join("a.cfg")).unwrap(), "new a");
+        assert_eq!(fs::read_to_string(dir.path().join("b.cfg")).unwrap(), "modified");
+        assert_eq!(fs::read_to_string(dir.path().join("b.cfg.new")).unwrap(), "new b");
+
+        // Backup file is recorded as extracted so it's replaced or removed in next update
+        let mut names = extracted.iter().map(|file| file.name.as_str()).collect::<Vec<_>>();
+        names.sort_unstable();
+        assert_eq!(names, ["a.cfg", "b.cfg", "b.cfg.new"]);
+        assert!(extracted.iter().all(|file| file.extracted));
+    }
+
+    #[tokio::test]
+    async fn write_nothing_if_resolve_fails() {
+        let dir = tempfile::tempdir().unwrap();
+        let api = CurseForgeAPI::new_client(Client::new(), CURSEFORGE_API_URL.into(), None);
+
+        let res = package(&[("manifest.json", MANIFEST), ("overrides/config/a.cfg", "a")])
+            .install(dir.path().to_path_buf(), &[], &api, 1)
+            .await;
+
+        assert!(matches!(res, Err(PackageInstallError::MissingApiKey)));
+        assert!(!dir.path().join("config").exists());
     }
 }
@@ -0,0 +1,150 @@
+/*
+ * Created on Mon May 24 2021
+ *
+ * Copyright (c) storycraft. Licensed under the GNU General Public License v3.
+ */
+
+//! Pack install plan checked against disk
+
+use std::{
+    collections::HashSet,
+    fs, io,
+    path::{Component, Path, PathBuf},
+};
+
+use serde::Serialize;
+
+use crate::{api::modpack::data::PackFile, util::file::check_file};
+
+/// Planned action of a pack file
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum FileAction {
+    /// File is missing or invalid so it will be downloaded
+    Download,
+
+    /// Valid file exists so it will be skipped
+    Valid,
+
+    /// File path is unsafe so it will be rejected
+    Reject,
+}
+
+/// Pack file with planned action
+#[derive(Debug, Clone, Serialize)]
+pub struct PlannedFile {
+    /// Pack file
+    pub file: PackFile,
+
+    /// Planned action
+    pub action: FileAction,
+}
+
+/// Install plan of pack files
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct FilePlan {
+    /// Pack files with planned action
+    pub files: Vec<PlannedFile>,
+
+    /// Existing files which are not part of the pack (relative to install location)
+    pub extra_files: Vec<PathBuf>,
+
+    /// Total size of files to download (byte)
+    pub download_size: u64,
+}
+
+impl FilePlan {
+    /// Check every file against install location.
+    /// Extra files are only searched in directories pack files are installed to.
+    pub fn check(files: &[PackFile], location: &Path) -> Result<Self, io::Error> {
+        let mut plan = FilePlan::default();
+
+        let mut pack_paths = HashSet::new();
+
+        for file in files {
+            let action = match file.info.install_path() {
+                Some(install_path) => {
+                    let action = if check_file(&location.join(&install_path), file.info.size, &file.info.sha1) {
+                        FileAction::Valid
+                    } else {
+                        FileAction::Download
+                    };
+
+                    pack_paths.insert(install_path);
+
+                    action
+                }
+
+                None => FileAction::Reject,
+            };
+
+            if action == FileAction::Download {
+                plan.download_size += file.info.size.unwrap_or(0);
+            }
+
+            plan.files.push(PlannedFile { file: file.clone(), action });
+        }
+
+        plan.extra_files = find_extra_files(location, &pack_paths)?;
+
+        Ok(plan)
+    }
+
+    /// Files planned with given action
+    pub fn with_action(&self, action: FileAction) -> impl Iterator<Item = &PlannedFile> {
+        self.files.iter().filter(move |planned| planned.action == action)
+    }
+}
+
+/// Find files not in pack paths (relative to location) in directories pack files are installed to.
+/// Files directly in location are not searched.
+pub fn find_extra_files(location: &Path, pack_paths: &HashSet<PathBuf>) -> Result<Vec<PathBuf>, io::Error> {
+    let pack_dirs = pack_paths
+        .iter()
+        .filter(|path| path.components().count() > 1)
+        .filter_map(|path| match path.components().next() {
+            Some(Component::Normal(dir)) => Some(PathBuf::from(dir)),
+            _ => None,
+        })
+        .collect::<HashSet<PathBuf>>();
+
+    let mut extra_files = Vec::new();
+
+    for dir in &pack_dirs {
+        for path in list_files(&location.join(dir))? {
+            if let Ok(relative) = path.strip_prefix(location) {
+                if !pack_paths.contains(relative) {
+                    extra_files.push(relative.to_path_buf());
+                }
+            }
+        }
+    }
+
+    extra_files.sort();
+
+    Ok(extra_files)
+}
+
+/// List every file in directory recursively. Returns empty list if the directory doesn't exist.
+pub fn list_files(dir: &Path) -> Result<Vec<PathBuf>, io::Error> {
+    let mut list = Vec::new();
+
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(list),
+        Err(err) => return Err(err),
+    };
+
+    for entry in entries {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+
+        if file_type.is_dir() {
+            list.extend(list_files(&entry.path())?);
+        } else if file_type.is_file() {
+            list.push(entry.path());
+        }
+    }
+
+    Ok(list)
+}
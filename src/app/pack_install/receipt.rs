@@ -0,0 +1,196 @@
+/*
+ * Created on Tue May 25 2021
+ *
+ * Copyright (c) storycraft. Licensed under the GNU General Public License v3.
+ */
+
+//! Install receipt written into install location
+
+use std::{
+    fs,
+    io::{self, BufReader, BufWriter},
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api::modpack::data::{unknown_size_as_none, PackFile, PackFileType, PackTarget, PackVersionData},
+    app::pack_select::ModPackVariant,
+    util::path::join_install_path,
+};
+
+use super::report::InstallReport;
+
+/// Receipt file name in install location
+pub const RECEIPT_FILE: &str = ".modpack-installer.json";
+
+/// Source of installed pack
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum PackSource {
+    /// modpacks.ch pack
+    ModPacks,
+
+    /// Curseforge pack from modpacks.ch
+    CurseForge,
+
+    /// Local overrides package
+    Package,
+}
+
+impl From<&ModPackVariant> for PackSource {
+    fn from(pack: &ModPackVariant) -> Self {
+        match pack {
+            ModPackVariant::ModPack(_) => PackSource::ModPacks,
+            ModPackVariant::CurseForge(_) => PackSource::CurseForge,
+        }
+    }
+}
+
+/// Installed file entry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReceiptFile {
+    /// File type
+    #[serde(rename = "type")]
+    pub file_type: PackFileType,
+
+    /// Relative path for file
+    pub path: String,
+
+    /// File name with extension
+    pub name: String,
+
+    /// File sha1 hash (hex)
+    pub sha1: String,
+
+    /// File size (byte). None if unknown.
+    #[serde(default, deserialize_with = "unknown_size_as_none")]
+    pub size: Option<u64>,
+
+    /// true if the file is extracted from overrides
+    #[serde(default)]
+    pub extracted: bool,
+}
+
+impl ReceiptFile {
+    /// Relative install path of the file including file name
+    pub fn install_path(&self) -> Option<PathBuf> {
+        join_install_path(&self.path, &self.name)
+    }
+}
+
+impl From<&PackFile> for ReceiptFile {
+    fn from(file: &PackFile) -> Self {
+        Self {
+            file_type: file.file_type,
+            path: file.info.path.clone(),
+            name: file.info.name.clone(),
+            sha1: file.info.sha1.clone(),
+            size: file.info.size,
+            extracted: false,
+        }
+    }
+}
+
+/// Install receipt of an instance
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InstallReceipt {
+    /// Pack source
+    pub source: PackSource,
+
+    /// Modpack id. 0 for packages.
+    pub pack_id: u32,
+
+    /// Pack name
+    pub name: String,
+
+    /// Version id. 0 for packages.
+    pub version_id: u32,
+
+    /// Version name
+    pub version_name: String,
+
+    /// Launch dependencies
+    pub targets: Vec<PackTarget>,
+
+    /// Installed files
+    pub files: Vec<ReceiptFile>,
+
+    /// true if some files failed to install. Failed files are not in files.
+    #[serde(default)]
+    pub incomplete: bool,
+}
+
+impl InstallReceipt {
+    /// Create receipt of installed pack version. Failed files are not recorded.
+    pub fn new(pack: &ModPackVariant, ver: &PackVersionData, report: &InstallReport) -> Self {
+        let info = pack.info();
+
+        Self {
+            source: pack.into(),
+            pack_id: info.id,
+            name: info.name.clone(),
+            version_id: ver.id,
+            version_name: ver.name.clone(),
+            targets: ver.targets.clone(),
+            files: installed_files(report),
+            incomplete: !report.is_complete(),
+        }
+    }
+
+    /// Create receipt of installed package. Failed files are not recorded.
+    pub fn new_package(name: String, version_name: String, targets: Vec<PackTarget>, report: &InstallReport) -> Self {
+        Self {
+            source: PackSource::Package,
+            pack_id: 0,
+            name,
+            version_id: 0,
+            version_name,
+            targets,
+            files: installed_files(report),
+            incomplete: !report.is_complete(),
+        }
+    }
+
+    /// Launcher profile key of installed pack
+    pub fn profile_key(&self) -> String {
+        match self.source {
+            PackSource::Package => format!("modpack-{}", self.name),
+            _ => format!("modpack-{}", self.pack_id),
+        }
+    }
+
+    /// Read receipt of install location
+    pub fn load(location: &Path) -> Result<Self, io::Error> {
+        let file = fs::File::open(location.join(RECEIPT_FILE))?;
+
+        Ok(serde_json::from_reader(BufReader::new(file))?)
+    }
+
+    /// Write receipt into install location
+    pub fn save(&self, location: &Path) -> Result<(), io::Error> {
+        fs::create_dir_all(location)?;
+
+        let file = fs::File::create(location.join(RECEIPT_FILE))?;
+
+        serde_json::to_writer_pretty(BufWriter::new(file), self)?;
+
+        Ok(())
+    }
+}
+
+fn installed_files(report: &InstallReport) -> Vec<ReceiptFile> {
+    report
+        .files
+        .iter()
+        .filter(|file_report| !file_report.is_failed())
+        .flat_map(|file_report| {
+            Some(ReceiptFile::from(&file_report.file))
+                .into_iter()
+                .chain(file_report.extracted.iter().cloned())
+        })
+        .collect()
+}
@@ -0,0 +1,125 @@
+/*
+ * Created on Fri May 21 2021
+ *
+ * Copyright (c) storycraft. Licensed under the GNU General Public License v3.
+ */
+
+//! Pack install result types
+
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::api::modpack::data::PackFile;
+
+use super::{receipt::ReceiptFile, web::FileInstallStatus};
+
+/// Install outcome of a pack file
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "status", content = "cause", rename_all = "snake_case")]
+pub enum FileInstallOutcome {
+    /// Downloaded and installed
+    Installed,
+
+    /// Valid file existed so skipped
+    SkippedValid,
+
+    /// Installed from content store
+    FromStore,
+
+    /// Failed to install with cause
+    Failed(String),
+}
+
+impl From<&FileInstallStatus> for FileInstallOutcome {
+    fn from(status: &FileInstallStatus) -> Self {
+        match status {
+            FileInstallStatus::ValidFileExists => FileInstallOutcome::SkippedValid,
+            FileInstallStatus::Installed => FileInstallOutcome::Installed,
+            FileInstallStatus::FromStore => FileInstallOutcome::FromStore,
+        }
+    }
+}
+
+/// Install result of a pack file
+#[derive(Debug, Clone, Serialize)]
+pub struct FileReport {
+    /// Pack file
+    pub file: PackFile,
+
+    /// Install outcome
+    pub outcome: FileInstallOutcome,
+
+    /// Bytes downloaded in this run
+    pub bytes: u64,
+
+    /// Time took to install (ms)
+    pub duration_ms: u64,
+
+    /// Files extracted from overrides
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub extracted: Vec<ReceiptFile>,
+}
+
+impl FileReport {
+    /// true if the file failed to install
+    pub fn is_failed(&self) -> bool {
+        matches!(self.outcome, FileInstallOutcome::Failed(_))
+    }
+}
+
+/// Install result of every pack file
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct InstallReport {
+    /// File results in completion order
+    pub files: Vec<FileReport>,
+}
+
+impl InstallReport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record file outcome
+    pub fn push(&mut self, file: PackFile, outcome: FileInstallOutcome, bytes: u64, duration: Duration) {
+        self.push_extracted(file, outcome, bytes, duration, Vec::new());
+    }
+
+    /// Record outcome of overrides file with files extracted from it
+    pub fn push_extracted(
+        &mut self,
+        file: PackFile,
+        outcome: FileInstallOutcome,
+        bytes: u64,
+        duration: Duration,
+        extracted: Vec<ReceiptFile>,
+    ) {
+        self.files.push(FileReport {
+            file,
+            outcome,
+            bytes,
+            duration_ms: duration.as_millis() as u64,
+            extracted,
+        });
+    }
+
+    /// Total downloaded bytes
+    pub fn downloaded_bytes(&self) -> u64 {
+        self.files.iter().map(|report| report.bytes).sum()
+    }
+
+    /// Files failed to install
+    pub fn failed(&self) -> impl Iterator<Item = &FileReport> {
+        self.files.iter().filter(|report| report.is_failed())
+    }
+
+    /// Required files failed to install
+    pub fn failed_required(&self) -> impl Iterator<Item = &FileReport> {
+        self.failed().filter(|report| !report.file.info.optional)
+    }
+
+    /// true if every file is installed or skipped
+    pub fn is_complete(&self) -> bool {
+        self.failed().next().is_none()
+    }
+}
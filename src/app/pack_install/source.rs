@@ -0,0 +1,450 @@
+/*
+ * Created on Sun May 30 2021
+ *
+ * Copyright (c) storycraft. Licensed under the GNU General Public License v3.
+ */
+
+//! Pack file sources install stream fetches file content from
+
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, BufReader, Read, SeekFrom},
+    path::{Path, PathBuf},
+};
+
+use bytes::Bytes;
+use futures::{
+    future::BoxFuture,
+    stream::{self, BoxStream},
+    StreamExt,
+};
+use reqwest::{header::RANGE, Client, StatusCode};
+use tokio::{
+    fs,
+    io::{AsyncReadExt, AsyncSeekExt},
+    sync::mpsc::{self, Sender},
+    task,
+};
+use zip::ZipArchive;
+
+use crate::{
+    api::modpack::data::PackFile,
+    util::{bandwidth::BandwidthLimiter, file::check_file},
+};
+
+use super::{bundle::bundle_entry_name, store::ContentStore, web::FileInstallError};
+
+/// Read buffer size of local sources
+const CHUNK_SIZE: usize = 8192;
+
+/// Fetched file content
+pub struct FileContent {
+    /// Offset content starts from. Can be 0 if the source cannot start from requested offset.
+    pub offset: u64,
+
+    /// Content byte stream
+    pub stream: BoxStream<'static, Result<Bytes, FileInstallError>>,
+}
+
+/// Source of pack file content
+pub trait FileSource: Send + Sync {
+    /// Fetch content of file starting from offset
+    fn fetch(&self, file: &PackFile, offset: u64) -> BoxFuture<'static, Result<FileContent, FileInstallError>>;
+
+    /// Install file to path without fetching content if the source keeps it locally.
+    /// Returns false if the file should be fetched instead.
+    fn install(&self, _file: &PackFile, _path: &Path, _copy_only: bool) -> BoxFuture<'static, Result<bool, FileInstallError>> {
+        Box::pin(async { Ok(false) })
+    }
+}
+
+/// Downloads files from pack file url
+#[derive(Debug, Clone)]
+pub struct HttpSource {
+    client: Client,
+
+    /// Bandwidth limiter shared by every request
+    limiter: Option<BandwidthLimiter>,
+}
+
+impl HttpSource {
+    pub fn new(client: Client) -> Self {
+        Self::new_limited(client, None)
+    }
+
+    /// Create source with bandwidth limiter
+    pub fn new_limited(client: Client, limiter: Option<BandwidthLimiter>) -> Self {
+        Self { client, limiter }
+    }
+}
+
+impl FileSource for HttpSource {
+    fn fetch(&self, file: &PackFile, offset: u64) -> BoxFuture<'static, Result<FileContent, FileInstallError>> {
+        let client = self.client.clone();
+        let url = file.info.url.clone();
+        let limiter = self.limiter.clone();
+
+        Box::pin(async move {
+            let (res, offset) = if offset > 0 {
+                // Server may ignore range and respond with full content
+                let res = client.get(&url).header(RANGE, format!("bytes={}-", offset)).send().await?;
+
+                match res.status() {
+                    StatusCode::PARTIAL_CONTENT => (res, offset),
+
+                    // Range is not satisfiable. Download from start.
+                    StatusCode::RANGE_NOT_SATISFIABLE => (client.get(&url).send().await?, 0),
+
+                    // Range is not supported
+                    _ => (res, 0),
+                }
+            } else {
+                (client.get(&url).send().await?, 0)
+            };
+
+            let res = res.error_for_status()?;
+
+            let stream = stream::unfold((res, limiter), |(mut res, limiter)| async move {
+                match res.chunk().await {
+                    Ok(Some(chunk)) => {
+                        if let Some(limiter) = &limiter {
+                            limiter.consume(chunk.len() as u64).await;
+                        }
+
+                        Some((Ok(chunk), (res, limiter)))
+                    }
+
+                    Ok(None) => None,
+
+                    Err(err) => Some((Err(err.into()), (res, limiter))),
+                }
+            });
+
+            Ok(FileContent { offset, stream: stream.boxed() })
+        })
+    }
+}
+
+/// Serves file content kept in memory keyed by file url
+#[derive(Debug, Clone, Default)]
+pub struct MemorySource {
+    files: HashMap<String, Bytes>,
+}
+
+impl MemorySource {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Serve content for file url
+    pub fn insert(&mut self, url: String, content: Bytes) {
+        self.files.insert(url, content);
+    }
+}
+
+impl FileSource for MemorySource {
+    fn fetch(&self, file: &PackFile, offset: u64) -> BoxFuture<'static, Result<FileContent, FileInstallError>> {
+        let content = self.files.get(&file.info.url).cloned();
+        let url = file.info.url.clone();
+
+        Box::pin(async move {
+            let content = content.ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("{} not found", url)))?;
+
+            // Offset past content is served from start like unsatisfiable range
+            let offset = if offset <= content.len() as u64 { offset } else { 0 };
+            let content = content.slice(offset as usize..);
+
+            let chunks = (0..content.len())
+                .step_by(CHUNK_SIZE)
+                .map(|start| Ok(content.slice(start..(start + CHUNK_SIZE).min(content.len()))))
+                .collect::<Vec<Result<Bytes, FileInstallError>>>();
+
+            Ok(FileContent { offset, stream: stream::iter(chunks).boxed() })
+        })
+    }
+}
+
+/// Reads files from directory laid out like install location
+#[derive(Debug, Clone)]
+pub struct DirSource {
+    dir: PathBuf,
+}
+
+impl DirSource {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+}
+
+impl FileSource for DirSource {
+    fn fetch(&self, file: &PackFile, offset: u64) -> BoxFuture<'static, Result<FileContent, FileInstallError>> {
+        let path = file.info.install_path().map(|install_path| self.dir.join(install_path));
+        let name = format!("{}/{}", file.info.path, file.info.name);
+
+        Box::pin(async move {
+            match path {
+                Some(path) => read_file(path, offset).await,
+                None => Err(FileInstallError::UnsafePath(name)),
+            }
+        })
+    }
+}
+
+/// Reads files from content store
+#[derive(Debug, Clone)]
+pub struct StoreSource {
+    store: ContentStore,
+}
+
+impl StoreSource {
+    pub fn new(store: ContentStore) -> Self {
+        Self { store }
+    }
+
+    pub fn store(&self) -> &ContentStore {
+        &self.store
+    }
+}
+
+impl FileSource for StoreSource {
+    fn fetch(&self, file: &PackFile, offset: u64) -> BoxFuture<'static, Result<FileContent, FileInstallError>> {
+        let path = self.store.blob_path(&file.info.sha1);
+        let sha1 = file.info.sha1.clone();
+
+        Box::pin(async move {
+            match path {
+                Some(path) => read_file(path, offset).await,
+                None => Err(io::Error::new(io::ErrorKind::NotFound, format!("blob {} not found", sha1)).into()),
+            }
+        })
+    }
+
+    /// Link or copy blob to path. Blob modified through linked file is removed so the file is fetched again.
+    fn install(&self, file: &PackFile, path: &Path, copy_only: bool) -> BoxFuture<'static, Result<bool, FileInstallError>> {
+        let store = self.store.clone();
+        let path = path.to_path_buf();
+        let size = file.info.size;
+        let sha1 = file.info.sha1.clone();
+
+        Box::pin(async move {
+            let installed = task::spawn_blocking(move || {
+                if !store.contains(&sha1, size) || store.install(&sha1, &path, copy_only).is_err() {
+                    return false;
+                }
+
+                if check_file(&path, size, &sha1) {
+                    return true;
+                }
+
+                std::fs::remove_file(&path).ok();
+                store.remove(&sha1).ok();
+
+                false
+            })
+            .await?;
+
+            Ok(installed)
+        })
+    }
+}
+
+/// Reads files from bundle created by export-bundle
+#[derive(Debug, Clone)]
+pub struct BundleSource {
+    bundle: PathBuf,
+}
+
+impl BundleSource {
+    pub fn new(bundle: PathBuf) -> Self {
+        Self { bundle }
+    }
+}
+
+impl FileSource for BundleSource {
+    fn fetch(&self, file: &PackFile, _: u64) -> BoxFuture<'static, Result<FileContent, FileInstallError>> {
+        let bundle = self.bundle.clone();
+        let entry = bundle_entry_name(&file.info);
+        let name = format!("{}/{}", file.info.path, file.info.name);
+
+        Box::pin(async move {
+            let entry = entry.ok_or(FileInstallError::UnsafePath(name))?;
+
+            // Zip entry is read on blocking thread pool and sent as chunks
+            let (sender, receiver) = mpsc::channel(4);
+
+            task::spawn_blocking(move || {
+                if let Err(err) = read_entry(&bundle, &entry, &sender) {
+                    sender.blocking_send(Err(err)).ok();
+                }
+            });
+
+            let stream = stream::unfold(receiver, |mut receiver| async move {
+                receiver.recv().await.map(|item| (item, receiver))
+            });
+
+            // Compressed entry cannot start from offset
+            Ok(FileContent { offset: 0, stream: stream.boxed() })
+        })
+    }
+}
+
+/// Stream local file content starting from offset
+async fn read_file(path: PathBuf, offset: u64) -> Result<FileContent, FileInstallError> {
+    let mut file = fs::File::open(path).await?;
+    file.seek(SeekFrom::Start(offset)).await?;
+
+    let stream = stream::unfold(file, |mut file| async move {
+        let mut buf = vec![0_u8; CHUNK_SIZE];
+
+        match file.read(&mut buf).await {
+            Ok(0) => None,
+
+            Ok(read) => {
+                buf.truncate(read);
+
+                Some((Ok(Bytes::from(buf)), file))
+            }
+
+            Err(err) => Some((Err(err.into()), file)),
+        }
+    });
+
+    Ok(FileContent { offset, stream: stream.boxed() })
+}
+
+/// Send bundle entry content as chunks. Stops if receiver is dropped. Blocks current thread.
+fn read_entry(
+    bundle: &Path,
+    entry: &str,
+    sender: &Sender<Result<Bytes, FileInstallError>>,
+) -> Result<(), FileInstallError> {
+    let mut archive = ZipArchive::new(BufReader::new(File::open(bundle)?))?;
+    let mut entry = archive.by_name(entry)?;
+
+    let mut buf = [0_u8; CHUNK_SIZE];
+    loop {
+        let read = entry.read(&mut buf)?;
+        if read == 0 {
+            break;
+        }
+
+        if sender.blocking_send(Ok(Bytes::copy_from_slice(&buf[..read]))).is_err() {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use futures::StreamExt;
+    use sha1::{Digest, Sha1};
+
+    use crate::api::modpack::data::{FileVersion, PackFile, PackFileInfo, PackFileType};
+
+    use super::{
+        super::{store::ContentStore, web::FileInstallError},
+        DirSource, FileContent, FileSource, StoreSource,
+    };
+
+    const CONTENT: &[u8] = b"mod file content";
+
+    fn pack_file(path: &str, name: &str) -> PackFile {
+        PackFile {
+            file_type: PackFileType::Mod,
+            info: PackFileInfo {
+                id: 0,
+                name: name.into(),
+                optional: false,
+                path: path.into(),
+                clientonly: false,
+                serveronly: false,
+                sha1: hex::encode(Sha1::digest(CONTENT)),
+                size: Some(CONTENT.len() as u64),
+                updated: 0,
+                url: String::new(),
+                version: FileVersion::Numberic(0),
+            },
+        }
+    }
+
+    async fn collect(mut content: FileContent) -> Vec<u8> {
+        let mut data = Vec::new();
+        while let Some(chunk) = content.stream.next().await {
+            data.extend_from_slice(&chunk.unwrap());
+        }
+
+        data
+    }
+
+    #[tokio::test]
+    async fn dir_source_reads_from_offset() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("mods")).unwrap();
+        fs::write(dir.path().join("mods").join("a.jar"), CONTENT).unwrap();
+
+        let source = DirSource::new(dir.path().to_path_buf());
+        let file = pack_file("./mods/", "a.jar");
+
+        assert_eq!(collect(source.fetch(&file, 0).await.unwrap()).await, CONTENT);
+
+        let content = source.fetch(&file, 4).await.unwrap();
+        assert_eq!(content.offset, 4);
+        assert_eq!(collect(content).await, &CONTENT[4..]);
+
+        assert!(matches!(
+            source.fetch(&pack_file("../mods", "a.jar"), 0).await,
+            Err(FileInstallError::UnsafePath(_))
+        ));
+        assert!(matches!(source.fetch(&pack_file("mods", "b.jar"), 0).await, Err(FileInstallError::Io(_))));
+    }
+
+    #[tokio::test]
+    async fn store_source_reads_blob() {
+        let store_dir = tempfile::tempdir().unwrap();
+        let store = ContentStore::new(store_dir.path().to_path_buf());
+        let file = pack_file("mods", "a.jar");
+
+        let source = StoreSource::new(store.clone());
+        assert!(source.fetch(&file, 0).await.is_err());
+
+        let stored = store_dir.path().join("a.jar");
+        fs::write(&stored, CONTENT).unwrap();
+        store.insert(&file.info.sha1, &stored, true).unwrap();
+
+        assert_eq!(collect(source.fetch(&file, 0).await.unwrap()).await, CONTENT);
+        assert_eq!(collect(source.fetch(&file, 4).await.unwrap()).await, &CONTENT[4..]);
+    }
+
+    #[tokio::test]
+    async fn store_source_installs_valid_blob() {
+        let store_dir = tempfile::tempdir().unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let store = ContentStore::new(store_dir.path().to_path_buf());
+        let file = pack_file("mods", "a.jar");
+        let path = dir.path().join("a.jar");
+
+        let source = StoreSource::new(store.clone());
+        assert!(!source.install(&file, &path, false).await.unwrap());
+        assert!(!path.exists());
+
+        let stored = store_dir.path().join("a.jar");
+        fs::write(&stored, CONTENT).unwrap();
+        store.insert(&file.info.sha1, &stored, true).unwrap();
+
+        assert!(source.install(&file, &path, true).await.unwrap());
+        assert_eq!(fs::read(&path).unwrap(), CONTENT);
+
+        // Blob modified through linked file is removed
+        let blob_path = store.blob_path(&file.info.sha1).unwrap();
+        fs::write(&blob_path, b"MOD FILE CONTENT").unwrap();
+
+        assert!(!source.install(&file, &path, true).await.unwrap());
+        assert!(!path.exists());
+        assert!(!blob_path.exists());
+    }
+}
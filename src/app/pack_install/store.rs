@@ -0,0 +1,222 @@
+/*
+ * Created on Fri May 28 2021
+ *
+ * Copyright (c) storycraft. Licensed under the GNU General Public License v3.
+ */
+
+//! Content addressed file store shared across instances
+
+use std::{
+    collections::HashSet,
+    fs,
+    io::{self, BufReader, BufWriter},
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::{plan::list_files, receipt::InstallReceipt};
+
+/// Instance list file name in store directory
+pub const INSTANCES_FILE: &str = "instances.json";
+
+/// Blob directory name in store directory
+pub const BLOBS_DIR: &str = "blobs";
+
+/// Install locations using store
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StoreInstances {
+    pub locations: Vec<PathBuf>,
+}
+
+/// Garbage collection result
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct GcReport {
+    /// Removed blob count
+    pub removed: usize,
+
+    /// Removed blob size (byte)
+    pub removed_bytes: u64,
+
+    /// Kept blob count
+    pub kept: usize,
+}
+
+/// Content addressed file store keyed by sha1.
+/// Every operation blocks current thread.
+#[derive(Debug, Clone)]
+pub struct ContentStore {
+    /// Store directory
+    dir: PathBuf,
+}
+
+impl ContentStore {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    /// Store directory
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Blob path of sha1 hash. Returns None if the hash is not valid hex sha1.
+    pub fn blob_path(&self, sha1: &str) -> Option<PathBuf> {
+        if sha1.len() != 40 || !sha1.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+
+        let sha1 = sha1.to_ascii_lowercase();
+
+        Some(self.dir.join(BLOBS_DIR).join(&sha1[..2]).join(sha1))
+    }
+
+    /// true if blob with sha1 and size exists.
+    /// Blob content is not hashed since only verified files are inserted.
+    pub fn contains(&self, sha1: &str, size: Option<u64>) -> bool {
+        match self.blob_path(sha1).and_then(|path| fs::metadata(path).ok()) {
+            Some(meta) => meta.is_file() && size.is_none_or(|size| meta.len() == size),
+            None => false,
+        }
+    }
+
+    /// Insert verified file into store. Existing blob is kept.
+    /// Hard link is used if possible and copy_only is false.
+    pub fn insert(&self, sha1: &str, path: &Path, copy_only: bool) -> Result<(), io::Error> {
+        let blob_path = match self.blob_path(sha1) {
+            Some(blob_path) => blob_path,
+            None => return Ok(()),
+        };
+
+        if blob_path.exists() {
+            return Ok(());
+        }
+
+        if let Some(parent) = blob_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        if copy_only || fs::hard_link(path, &blob_path).is_err() {
+            // Copy to temporary file first so incomplete blob is never visible
+            let part_path = blob_path.with_extension("part");
+
+            fs::copy(path, &part_path)?;
+            fs::rename(part_path, blob_path)?;
+        }
+
+        Ok(())
+    }
+
+    /// Install blob to path. Hard link is used if possible and copy_only is false.
+    /// Files which may be modified after install should be copied so the blob stays intact.
+    pub fn install(&self, sha1: &str, path: &Path, copy_only: bool) -> Result<(), io::Error> {
+        let blob_path = self
+            .blob_path(sha1)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("blob {} not found", sha1)))?;
+
+        match fs::remove_file(path) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+            _ => {}
+        }
+
+        if copy_only || fs::hard_link(&blob_path, path).is_err() {
+            fs::copy(blob_path, path)?;
+        }
+
+        Ok(())
+    }
+
+    /// Remove blob of sha1. Missing blob is ignored.
+    pub fn remove(&self, sha1: &str) -> Result<(), io::Error> {
+        let blob_path = match self.blob_path(sha1) {
+            Some(blob_path) => blob_path,
+            None => return Ok(()),
+        };
+
+        match fs::remove_file(blob_path) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+            _ => Ok(()),
+        }
+    }
+
+    /// Read install locations using store
+    pub fn instances(&self) -> Result<StoreInstances, io::Error> {
+        match fs::File::open(self.dir.join(INSTANCES_FILE)) {
+            Ok(file) => Ok(serde_json::from_reader(BufReader::new(file))?),
+
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(StoreInstances::default()),
+
+            Err(err) => Err(err),
+        }
+    }
+
+    fn save_instances(&self, instances: &StoreInstances) -> Result<(), io::Error> {
+        fs::create_dir_all(&self.dir)?;
+
+        let file = fs::File::create(self.dir.join(INSTANCES_FILE))?;
+        serde_json::to_writer_pretty(BufWriter::new(file), instances)?;
+
+        Ok(())
+    }
+
+    /// Register install location using store so its files are kept on garbage collection
+    pub fn register(&self, location: &Path) -> Result<(), io::Error> {
+        let location = location.canonicalize()?;
+
+        let mut instances = self.instances()?;
+
+        if !instances.locations.contains(&location) {
+            instances.locations.push(location);
+            self.save_instances(&instances)?;
+        }
+
+        Ok(())
+    }
+
+    /// Remove blobs not referenced by receipt of any registered install location.
+    /// Install locations without receipt are unregistered. Nothing is removed if dry_run is true.
+    pub fn gc(&self, dry_run: bool) -> Result<GcReport, io::Error> {
+        let mut instances = self.instances()?;
+        let mut referenced = HashSet::new();
+        let mut locations = Vec::new();
+
+        for location in instances.locations {
+            match InstallReceipt::load(&location) {
+                Ok(receipt) => {
+                    referenced.extend(receipt.files.into_iter().map(|file| file.sha1.to_ascii_lowercase()));
+                    locations.push(location);
+                }
+
+                Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+
+                Err(err) => return Err(err),
+            }
+        }
+
+        instances.locations = locations;
+
+        let mut report = GcReport::default();
+
+        for path in list_files(&self.dir.join(BLOBS_DIR))? {
+            let name = path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+
+            if referenced.contains(&name) {
+                report.kept += 1;
+                continue;
+            }
+
+            report.removed += 1;
+            report.removed_bytes += fs::metadata(&path).map(|meta| meta.len()).unwrap_or(0);
+
+            if !dry_run {
+                fs::remove_file(path)?;
+            }
+        }
+
+        if !dry_run {
+            self.save_instances(&instances)?;
+        }
+
+        Ok(report)
+    }
+}
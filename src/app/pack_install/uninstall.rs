@@ -0,0 +1,203 @@
+/*
+ * Created on Wed May 26 2021
+ *
+ * Copyright (c) storycraft. Licensed under the GNU General Public License v3.
+ */
+
+//! Installed pack removal
+
+use std::{fs, io, path::Path};
+
+use crate::launcher::profile::GameLaunchProfile;
+
+use super::receipt::{ReceiptFile, RECEIPT_FILE};
+
+/// Saves directory name in install location
+pub const SAVES_DIR: &str = "saves";
+
+/// Delete installed files and receipt. Returns deleted file count.
+/// Files already deleted are ignored and directories left empty are removed.
+pub fn remove_pack_files(location: &Path, files: &[ReceiptFile]) -> Result<usize, io::Error> {
+    let mut count = 0;
+
+    for install_path in files.iter().filter_map(ReceiptFile::install_path) {
+        let path = location.join(&install_path);
+
+        match fs::remove_file(&path) {
+            Ok(_) => count += 1,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+            Err(err) => return Err(err),
+        }
+
+        // Remove empty parent directories inside install location
+        for dir in path.ancestors().skip(1) {
+            if dir == location || fs::remove_dir(dir).is_err() {
+                break;
+            }
+        }
+    }
+
+    match fs::remove_file(location.join(RECEIPT_FILE)) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+        _ => {}
+    }
+
+    Ok(count)
+}
+
+/// true if game profile launches game in install location.
+/// Profiles of same pack can point to other install locations.
+pub fn is_instance_profile(profile: &GameLaunchProfile, location: &Path) -> bool {
+    let game_dir = match &profile.game_dir {
+        Some(game_dir) => Path::new(game_dir),
+        None => return false,
+    };
+
+    match (game_dir.canonicalize(), location.canonicalize()) {
+        (Ok(game_dir), Ok(location)) => game_dir == location,
+        _ => game_dir == location,
+    }
+}
+
+/// Delete whole install location. Saves directory is kept unless keep_saves is false.
+pub fn remove_instance(location: &Path, keep_saves: bool) -> Result<(), io::Error> {
+    if !keep_saves || !location.join(SAVES_DIR).exists() {
+        return match fs::remove_dir_all(location) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+            _ => Ok(()),
+        };
+    }
+
+    for entry in fs::read_dir(location)? {
+        let entry = entry?;
+
+        if entry.file_name() == SAVES_DIR {
+            continue;
+        }
+
+        if entry.file_type()?.is_dir() {
+            fs::remove_dir_all(entry.path())?;
+        } else {
+            fs::remove_file(entry.path())?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, fs, path::Path};
+
+    use crate::{
+        api::modpack::data::PackFileType,
+        launcher::profile::GameLaunchProfile,
+    };
+
+    use super::{is_instance_profile, remove_instance, remove_pack_files, ReceiptFile, RECEIPT_FILE, SAVES_DIR};
+
+    fn receipt_file(path: &str, name: &str) -> ReceiptFile {
+        ReceiptFile {
+            file_type: PackFileType::Mod,
+            path: path.into(),
+            name: name.into(),
+            sha1: String::new(),
+            size: None,
+            extracted: false,
+        }
+    }
+
+    fn write(location: &Path, path: &str) {
+        let path = location.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, "data").unwrap();
+    }
+
+    fn profile(game_dir: Option<&Path>) -> GameLaunchProfile {
+        GameLaunchProfile {
+            created: None,
+            last_used: None,
+            game_dir: game_dir.map(|dir| dir.to_string_lossy().into()),
+            java_args: None,
+            last_version_id: String::new(),
+            icon: None,
+            name: "pack".into(),
+            profile_type: "custom".into(),
+            extra: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn remove_files_and_empty_dirs() {
+        let dir = tempfile::tempdir().unwrap();
+
+        write(dir.path(), "mods/a.jar");
+        write(dir.path(), "config/sub/a.cfg");
+        write(dir.path(), "config/user.cfg");
+        write(dir.path(), RECEIPT_FILE);
+
+        let files = vec![
+            receipt_file("mods", "a.jar"),
+            receipt_file("config/sub", "a.cfg"),
+            receipt_file("mods", "missing.jar"),
+        ];
+
+        assert_eq!(remove_pack_files(dir.path(), &files).unwrap(), 2);
+
+        assert!(!dir.path().join("mods").exists());
+        assert!(!dir.path().join("config").join("sub").exists());
+        assert!(dir.path().join("config").join("user.cfg").exists());
+        assert!(!dir.path().join(RECEIPT_FILE).exists());
+        assert!(dir.path().exists());
+    }
+
+    #[test]
+    fn remove_instance_keeping_saves() {
+        let dir = tempfile::tempdir().unwrap();
+        let location = dir.path().join("pack");
+
+        write(&location, "mods/a.jar");
+        write(&location, "options.txt");
+        write(&location, "saves/world/level.dat");
+
+        remove_instance(&location, true).unwrap();
+
+        let names = fs::read_dir(&location)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect::<Vec<_>>();
+
+        assert_eq!(names, [SAVES_DIR]);
+        assert!(location.join("saves").join("world").join("level.dat").exists());
+    }
+
+    #[test]
+    fn remove_whole_instance() {
+        let dir = tempfile::tempdir().unwrap();
+        let location = dir.path().join("pack");
+
+        write(&location, "mods/a.jar");
+        write(&location, "saves/world/level.dat");
+
+        remove_instance(&location, false).unwrap();
+        assert!(!location.exists());
+
+        // Already removed location is ignored
+        remove_instance(&location, true).unwrap();
+    }
+
+    #[test]
+    fn match_profile_game_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let location = dir.path().join("pack");
+        let other = dir.path().join("other");
+
+        fs::create_dir_all(&location).unwrap();
+        fs::create_dir_all(&other).unwrap();
+
+        assert!(is_instance_profile(&profile(Some(&location)), &location));
+        assert!(is_instance_profile(&profile(Some(&location.join("."))), &location));
+        assert!(!is_instance_profile(&profile(Some(&other)), &location));
+        assert!(!is_instance_profile(&profile(None), &location));
+    }
+}
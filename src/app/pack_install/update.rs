@@ -0,0 +1,504 @@
+/*
+ * Created on Tue May 25 2021
+ *
+ * Copyright (c) storycraft. Licensed under the GNU General Public License v3.
+ */
+
+//! Installed pack update diff
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs, io,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use serde::Serialize;
+
+use crate::{
+    api::modpack::data::{PackFile, PackFileType},
+    util::file::check_file,
+};
+
+use super::receipt::ReceiptFile;
+
+/// Update action of a config file modified by user
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfigAction {
+    /// Keep modified file
+    Keep,
+
+    /// Overwrite with new pack file
+    Overwrite,
+
+    /// Keep modified file and install new pack file as `<name>.new`
+    Backup,
+}
+
+impl FromStr for ConfigAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "keep" => Ok(ConfigAction::Keep),
+            "overwrite" => Ok(ConfigAction::Overwrite),
+            "backup" => Ok(ConfigAction::Backup),
+            _ => Err(format!("unknown config action: {}", s)),
+        }
+    }
+}
+
+/// Config file modified by user since installed
+#[derive(Debug, Clone)]
+pub struct ModifiedConfig {
+    /// Changed pack file. None if the file is extracted from overrides.
+    pub file: Option<PackFile>,
+
+    /// Previously installed entry
+    pub previous: ReceiptFile,
+}
+
+/// File difference between installed files and new pack version files
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct FileDiff {
+    /// Files not installed previously
+    pub added: Vec<PackFile>,
+
+    /// Files installed previously with different hash
+    pub changed: Vec<PackFile>,
+
+    /// Previously installed files removed from the pack
+    pub removed: Vec<ReceiptFile>,
+
+    /// Files installed previously with same hash
+    pub unchanged: Vec<PackFile>,
+
+    /// Previously installed files kept as is
+    pub kept: Vec<ReceiptFile>,
+
+    /// Files extracted from previously installed overrides
+    #[serde(skip)]
+    pub extracted: Vec<ReceiptFile>,
+
+    /// Actions applied to modified extracted files when new overrides are extracted, keyed by install path
+    #[serde(skip)]
+    pub extract_actions: HashMap<PathBuf, ConfigAction>,
+}
+
+impl FileDiff {
+    /// Compare installed files and new files by install path and sha1.
+    /// Files with unsafe path are ignored. Extracted files are not compared since they belong to overrides.
+    pub fn new(installed: &[ReceiptFile], files: &[PackFile]) -> Self {
+        let mut diff = FileDiff::default();
+
+        let (extracted, installed) = installed.iter().cloned().partition::<Vec<_>, _>(|file| file.extracted);
+        diff.extracted = extracted;
+
+        let mut previous = installed
+            .iter()
+            .filter_map(|file| Some((file.install_path()?, file)))
+            .collect::<HashMap<_, _>>();
+
+        for file in files {
+            let install_path = match file.info.install_path() {
+                Some(install_path) => install_path,
+                None => continue,
+            };
+
+            match previous.remove(&install_path) {
+                Some(old) if old.sha1.eq_ignore_ascii_case(&file.info.sha1) => diff.unchanged.push(file.clone()),
+
+                Some(_) => diff.changed.push(file.clone()),
+
+                None => diff.added.push(file.clone()),
+            }
+        }
+
+        diff.removed = installed
+            .iter()
+            .filter(|file| match file.install_path() {
+                Some(install_path) => previous.contains_key(&install_path),
+                None => false,
+            })
+            .cloned()
+            .collect();
+
+        diff
+    }
+
+    /// Changed config files and files extracted from replaced overrides modified since installed
+    pub fn modified_configs(&self, installed: &[ReceiptFile], location: &Path) -> Vec<ModifiedConfig> {
+        let previous = installed
+            .iter()
+            .filter_map(|file| Some((file.install_path()?, file)))
+            .collect::<HashMap<_, _>>();
+
+        let is_modified = |file: &ReceiptFile, install_path: &Path| {
+            let path = location.join(install_path);
+
+            path.is_file() && !check_file(&path, file.size, &file.sha1)
+        };
+
+        let changed = self
+            .changed
+            .iter()
+            .filter(|file| matches!(file.file_type, PackFileType::Config))
+            .filter_map(|file| {
+                let install_path = file.info.install_path()?;
+                let old = previous.get(&install_path)?;
+
+                if is_modified(old, &install_path) {
+                    Some(ModifiedConfig { file: Some(file.clone()), previous: (*old).clone() })
+                } else {
+                    None
+                }
+            });
+
+        // Extracted files are only replaced if overrides changed
+        let extracted = if self.overrides_changed() { &self.extracted[..] } else { &[] };
+
+        let extracted = extracted
+            .iter()
+            .filter(|file| file.install_path().is_some_and(|install_path| is_modified(file, &install_path)))
+            .map(|file| ModifiedConfig { file: None, previous: file.clone() });
+
+        changed.chain(extracted).collect()
+    }
+
+    /// Apply action to modified config file.
+    /// Backup file is installed as pack file so it's replaced or removed in next update.
+    /// Action of extracted file is applied when new overrides are extracted.
+    pub fn resolve_config(&mut self, config: ModifiedConfig, action: ConfigAction) {
+        let file = match config.file {
+            Some(file) => file,
+
+            None => {
+                if let Some(install_path) = config.previous.install_path() {
+                    self.extract_actions.insert(install_path, action);
+                }
+
+                return;
+            }
+        };
+
+        if action == ConfigAction::Overwrite {
+            return;
+        }
+
+        self.changed.retain(|changed| changed.info.install_path() != file.info.install_path());
+        self.kept.push(config.previous);
+
+        if action == ConfigAction::Backup {
+            let mut backup = file;
+            backup.info.name = format!("{}.new", backup.info.name);
+
+            self.added.push(backup);
+        }
+    }
+
+    /// Files to download
+    pub fn downloads(&self) -> Vec<PackFile> {
+        self.added.iter().chain(self.changed.iter()).cloned().collect()
+    }
+
+    /// true if nothing changed
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.changed.is_empty() && self.removed.is_empty()
+    }
+
+    /// true if overrides are added, changed or removed so previously extracted files are replaced
+    pub fn overrides_changed(&self) -> bool {
+        self.downloads()
+            .iter()
+            .any(|file| matches!(file.file_type, PackFileType::Overrides))
+            || self
+                .removed
+                .iter()
+                .any(|file| matches!(file.file_type, PackFileType::Overrides))
+    }
+
+    /// Previously installed files no longer part of the pack, including replaced extracted files
+    pub fn stale_files(&self) -> impl Iterator<Item = &ReceiptFile> {
+        let extracted = if self.overrides_changed() { &self.extracted[..] } else { &[] };
+
+        self.removed.iter().chain(extracted)
+    }
+
+    /// Stale files not replaced by installed files at same path.
+    /// These are still on disk until removed so failed update should keep them in receipt.
+    pub fn remaining_stale_files(&self, installed: &[ReceiptFile]) -> Vec<ReceiptFile> {
+        let installed_paths = installed.iter().filter_map(ReceiptFile::install_path).collect::<HashSet<_>>();
+
+        self.stale_files()
+            .filter(|file| file.install_path().is_some_and(|path| !installed_paths.contains(&path)))
+            .cloned()
+            .collect()
+    }
+
+    /// Delete stale files not replaced by installed files from install location. Files already deleted are ignored.
+    /// Config files modified since installed are not deleted.
+    pub fn remove_files(&self, location: &Path, installed: &[ReceiptFile]) -> Result<(), io::Error> {
+        for file in self.remaining_stale_files(installed) {
+            if let Some(install_path) = file.install_path() {
+                let path = location.join(install_path);
+
+                if matches!(file.file_type, PackFileType::Config) && path.is_file() && !check_file(&path, file.size, &file.sha1) {
+                    continue;
+                }
+
+                match fs::remove_file(path) {
+                    Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+                    _ => {}
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::Path};
+
+    use sha1::{Digest, Sha1};
+
+    use crate::api::modpack::data::{FileVersion, PackFile, PackFileInfo, PackFileType};
+
+    use super::{ConfigAction, FileDiff, ModifiedConfig, ReceiptFile};
+
+    fn sha1(content: &str) -> String {
+        hex::encode(Sha1::digest(content.as_bytes()))
+    }
+
+    fn pack_file(file_type: PackFileType, path: &str, name: &str, content: &str) -> PackFile {
+        PackFile {
+            file_type,
+            info: PackFileInfo {
+                id: 0,
+                name: name.into(),
+                optional: false,
+                path: path.into(),
+                clientonly: false,
+                serveronly: false,
+                sha1: sha1(content),
+                size: Some(content.len() as u64),
+                updated: 0,
+                url: String::new(),
+                version: FileVersion::Numberic(0),
+            },
+        }
+    }
+
+    fn extracted_file(path: &str, name: &str, content: &str) -> ReceiptFile {
+        ReceiptFile {
+            extracted: true,
+            ..ReceiptFile::from(&pack_file(PackFileType::Config, path, name, content))
+        }
+    }
+
+    /// Write file and returns its receipt entry
+    fn install(location: &Path, file: &PackFile, content: &str) -> ReceiptFile {
+        let path = location.join(file.info.install_path().unwrap());
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+
+        ReceiptFile::from(file)
+    }
+
+    fn names<'a>(files: impl IntoIterator<Item = &'a PackFile>) -> Vec<&'a str> {
+        files.into_iter().map(|file| file.info.name.as_str()).collect()
+    }
+
+    #[test]
+    fn classify_files() {
+        let installed = vec![
+            ReceiptFile::from(&pack_file(PackFileType::Mod, "mods", "a.jar", "a")),
+            ReceiptFile::from(&pack_file(PackFileType::Mod, "mods", "b.jar", "b")),
+            ReceiptFile::from(&pack_file(PackFileType::Mod, "mods", "c.jar", "c")),
+        ];
+
+        let files = vec![
+            pack_file(PackFileType::Mod, "mods", "a.jar", "a"),
+            pack_file(PackFileType::Mod, "./mods/", "b.jar", "new b"),
+            pack_file(PackFileType::Mod, "mods", "d.jar", "d"),
+        ];
+
+        let diff = FileDiff::new(&installed, &files);
+
+        assert_eq!(names(&diff.unchanged), ["a.jar"]);
+        assert_eq!(names(&diff.changed), ["b.jar"]);
+        assert_eq!(names(&diff.added), ["d.jar"]);
+        assert_eq!(diff.removed.len(), 1);
+        assert_eq!(diff.removed[0].name, "c.jar");
+        assert!(diff.extracted.is_empty());
+        assert!(!diff.is_empty());
+    }
+
+    #[test]
+    fn remove_only_stale_pack_files() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let kept = pack_file(PackFileType::Mod, "mods", "a.jar", "a");
+        let removed = pack_file(PackFileType::Mod, "mods", "b.jar", "b");
+        let modified_config = pack_file(PackFileType::Config, "config", "b.cfg", "b");
+
+        let installed = vec![
+            install(dir.path(), &kept, "a"),
+            install(dir.path(), &removed, "b"),
+            install(dir.path(), &modified_config, "modified"),
+        ];
+
+        // Created by user and not part of the pack
+        fs::write(dir.path().join("mods").join("user.jar"), "user").unwrap();
+
+        let diff = FileDiff::new(&installed, &[kept]);
+        diff.remove_files(dir.path(), &[]).unwrap();
+
+        assert!(dir.path().join("mods").join("a.jar").exists());
+        assert!(!dir.path().join("mods").join("b.jar").exists());
+        assert!(dir.path().join("mods").join("user.jar").exists());
+        assert_eq!(fs::read_to_string(dir.path().join("config").join("b.cfg")).unwrap(), "modified");
+    }
+
+    #[test]
+    fn keep_extracted_files_if_overrides_unchanged() {
+        let overrides = pack_file(PackFileType::Overrides, "", "overrides.zip", "overrides");
+
+        let installed = vec![ReceiptFile::from(&overrides), extracted_file("config", "a.cfg", "a")];
+
+        let diff = FileDiff::new(&installed, &[overrides]);
+
+        assert!(!diff.overrides_changed());
+        assert_eq!(diff.extracted.len(), 1);
+        assert_eq!(diff.stale_files().count(), 0);
+    }
+
+    #[test]
+    fn remove_extracted_files_if_overrides_changed() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let old_overrides = pack_file(PackFileType::Overrides, "", "overrides.zip", "overrides");
+        let new_overrides = pack_file(PackFileType::Overrides, "", "overrides.zip", "new overrides");
+
+        let reextracted = extracted_file("config", "a.cfg", "a");
+        let dropped = extracted_file("config", "b.cfg", "b");
+
+        fs::create_dir_all(dir.path().join("config")).unwrap();
+        fs::write(dir.path().join("config").join("a.cfg"), "a").unwrap();
+        fs::write(dir.path().join("config").join("b.cfg"), "b").unwrap();
+
+        let installed = vec![ReceiptFile::from(&old_overrides), reextracted.clone(), dropped.clone()];
+
+        let diff = FileDiff::new(&installed, &[new_overrides]);
+
+        assert!(diff.overrides_changed());
+        assert_eq!(names(&diff.changed), ["overrides.zip"]);
+        assert!(diff.removed.is_empty());
+        assert_eq!(diff.stale_files().count(), 2);
+
+        // New overrides extracted a.cfg again
+        diff.remove_files(dir.path(), &[extracted_file("config", "a.cfg", "new a")]).unwrap();
+
+        assert!(dir.path().join("config").join("a.cfg").exists());
+        assert!(!dir.path().join("config").join("b.cfg").exists());
+    }
+
+    #[test]
+    fn failed_update_keeps_stale_files() {
+        let old = pack_file(PackFileType::Mod, "mods", "a.jar", "a");
+        let replaced = pack_file(PackFileType::Mod, "mods", "b.jar", "b");
+
+        let installed = vec![ReceiptFile::from(&old), ReceiptFile::from(&replaced)];
+        let diff = FileDiff::new(&installed, &[]);
+
+        // Only b.jar was installed again before the update failed
+        let remaining = diff.remaining_stale_files(&[ReceiptFile::from(&replaced)]);
+
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].name, "a.jar");
+    }
+
+    #[test]
+    fn list_modified_extracted_files() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let old_overrides = pack_file(PackFileType::Overrides, "", "overrides.zip", "overrides");
+        let new_overrides = pack_file(PackFileType::Overrides, "", "overrides.zip", "new overrides");
+
+        let modified = extracted_file("config", "a.cfg", "a");
+        let unmodified = extracted_file("config", "b.cfg", "b");
+
+        fs::create_dir_all(dir.path().join("config")).unwrap();
+        fs::write(dir.path().join("config").join("a.cfg"), "modified").unwrap();
+        fs::write(dir.path().join("config").join("b.cfg"), "b").unwrap();
+
+        let installed = vec![ReceiptFile::from(&old_overrides), modified, unmodified];
+
+        // Extracted files are not replaced if overrides are unchanged
+        let diff = FileDiff::new(&installed, &[old_overrides]);
+        assert!(diff.modified_configs(&installed, dir.path()).is_empty());
+
+        let mut diff = FileDiff::new(&installed, &[new_overrides]);
+        let mut configs = diff.modified_configs(&installed, dir.path());
+
+        assert_eq!(configs.len(), 1);
+        assert!(configs[0].file.is_none());
+        assert_eq!(configs[0].previous.name, "a.cfg");
+
+        diff.resolve_config(configs.remove(0), ConfigAction::Backup);
+
+        assert_eq!(diff.extract_actions.get(&Path::new("config").join("a.cfg")), Some(&ConfigAction::Backup));
+        assert_eq!(names(&diff.changed), ["overrides.zip"]);
+    }
+
+    /// Diff of changed config file with its modified config entry
+    fn modified_config_diff() -> (FileDiff, ModifiedConfig) {
+        let old = pack_file(PackFileType::Config, "config", "a.cfg", "a");
+        let new = pack_file(PackFileType::Config, "config", "a.cfg", "new a");
+
+        let installed = vec![ReceiptFile::from(&old)];
+        let diff = FileDiff::new(&installed, std::slice::from_ref(&new));
+
+        (diff, ModifiedConfig { file: Some(new), previous: installed[0].clone() })
+    }
+
+    #[test]
+    fn resolve_keep() {
+        let (mut diff, config) = modified_config_diff();
+
+        diff.resolve_config(config, ConfigAction::Keep);
+
+        assert!(diff.changed.is_empty());
+        assert!(diff.added.is_empty());
+        assert_eq!(diff.kept.len(), 1);
+        assert_eq!(diff.kept[0].sha1, sha1("a"));
+        assert!(diff.downloads().is_empty());
+    }
+
+    #[test]
+    fn resolve_backup() {
+        let (mut diff, config) = modified_config_diff();
+
+        diff.resolve_config(config, ConfigAction::Backup);
+
+        assert!(diff.changed.is_empty());
+        assert_eq!(diff.kept.len(), 1);
+        assert_eq!(diff.kept[0].sha1, sha1("a"));
+        assert_eq!(names(&diff.added), ["a.cfg.new"]);
+        assert_eq!(diff.added[0].info.sha1, sha1("new a"));
+    }
+
+    #[test]
+    fn resolve_overwrite() {
+        let (mut diff, config) = modified_config_diff();
+
+        diff.resolve_config(config, ConfigAction::Overwrite);
+
+        assert_eq!(names(&diff.changed), ["a.cfg"]);
+        assert!(diff.added.is_empty());
+        assert!(diff.kept.is_empty());
+        assert_eq!(names(&diff.downloads()), ["a.cfg"]);
+    }
+}
@@ -0,0 +1,247 @@
+/*
+ * Created on Wed May 26 2021
+ *
+ * Copyright (c) storycraft. Licensed under the GNU General Public License v3.
+ */
+
+//! Installed pack verification
+
+use std::{
+    collections::HashSet,
+    io,
+    path::{Path, PathBuf},
+};
+
+use futures::{stream, StreamExt};
+use serde::Serialize;
+use tokio::task;
+
+use crate::{api::modpack::data::PackFileType, util::file::check_file};
+
+use super::{plan::find_extra_files, receipt::ReceiptFile};
+
+/// Verified state of an installed file
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum FileState {
+    /// File exists with same size and hash
+    Valid,
+
+    /// File doesn't exist
+    Missing,
+
+    /// File exists with different size or hash
+    Corrupt,
+
+    /// Config file edited by user since installed. Not an error.
+    Modified,
+}
+
+/// Installed file with verified state
+#[derive(Debug, Clone, Serialize)]
+pub struct VerifiedFile {
+    /// Installed file
+    pub file: ReceiptFile,
+
+    /// Verified state
+    pub state: FileState,
+}
+
+impl VerifiedFile {
+    /// true if the file is missing or corrupt
+    pub fn is_bad(&self) -> bool {
+        matches!(self.state, FileState::Missing | FileState::Corrupt)
+    }
+}
+
+/// Verification result of installed files
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct VerifyReport {
+    /// Installed files with verified state
+    pub files: Vec<VerifiedFile>,
+
+    /// Existing files which are not part of the pack (relative to install location)
+    pub extra_files: Vec<PathBuf>,
+}
+
+impl VerifyReport {
+    /// Missing or corrupt files
+    pub fn bad(&self) -> impl Iterator<Item = &VerifiedFile> {
+        self.files.iter().filter(|verified| verified.is_bad())
+    }
+
+    /// Config files edited by user
+    pub fn modified(&self) -> impl Iterator<Item = &VerifiedFile> {
+        self.files.iter().filter(|verified| verified.state == FileState::Modified)
+    }
+}
+
+/// Verify installed files on blocking thread pool with concurrency.
+/// Files with unsafe path are ignored.
+pub async fn verify_files(location: PathBuf, files: Vec<ReceiptFile>, concurrency: usize) -> Result<VerifyReport, io::Error> {
+    verify_files_with(location, files, concurrency, verify_file).await
+}
+
+/// Verify installed files using given file check function
+async fn verify_files_with<F>(
+    location: PathBuf,
+    files: Vec<ReceiptFile>,
+    concurrency: usize,
+    verify: F,
+) -> Result<VerifyReport, io::Error>
+where
+    F: Fn(&Path, &ReceiptFile) -> FileState + Clone + Send + 'static,
+{
+    let files = files
+        .into_iter()
+        .filter_map(|file| Some((file.install_path()?, file)))
+        .collect::<Vec<(PathBuf, ReceiptFile)>>();
+
+    let pack_paths = files.iter().map(|(path, _)| path.clone()).collect::<HashSet<PathBuf>>();
+
+    let verify_stream = stream::iter(files).map(|(install_path, file)| {
+        let path = location.join(install_path);
+        let verify = verify.clone();
+
+        task::spawn_blocking(move || {
+            let state = verify(&path, &file);
+
+            VerifiedFile { file, state }
+        })
+    });
+
+    let mut report = VerifyReport {
+        files: verify_stream
+            .buffer_unordered(concurrency.max(1))
+            .collect::<Vec<Result<VerifiedFile, task::JoinError>>>()
+            .await
+            .into_iter()
+            .collect::<Result<Vec<VerifiedFile>, task::JoinError>>()
+            .map_err(io::Error::other)?,
+        extra_files: Vec::new(),
+    };
+
+    report.extra_files = task::spawn_blocking(move || find_extra_files(&location, &pack_paths))
+        .await
+        .map_err(io::Error::other)??;
+
+    Ok(report)
+}
+
+/// Config files including extracted files are expected to be edited so they are reported as modified
+fn verify_file(path: &Path, file: &ReceiptFile) -> FileState {
+    if !path.is_file() {
+        FileState::Missing
+    } else if check_file(path, file.size, &file.sha1) {
+        FileState::Valid
+    } else if matches!(file.file_type, PackFileType::Config) {
+        FileState::Modified
+    } else {
+        FileState::Corrupt
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        path::Path,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        thread,
+        time::{Duration, Instant},
+    };
+
+    use crate::api::modpack::data::PackFileType;
+
+    use super::{verify_files, verify_files_with, FileState, ReceiptFile};
+
+    fn receipt_file(name: &str) -> ReceiptFile {
+        ReceiptFile {
+            file_type: PackFileType::Mod,
+            path: "mods".into(),
+            name: name.into(),
+            sha1: String::new(),
+            size: None,
+            extracted: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn verify_checks_files_concurrently() {
+        let dir = tempfile::tempdir().unwrap();
+        let files = (0..8).map(|i| receipt_file(&format!("{}.jar", i))).collect::<Vec<ReceiptFile>>();
+
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let max_in_flight = Arc::new(AtomicUsize::new(0));
+
+        let verify = {
+            let in_flight = in_flight.clone();
+            let max_in_flight = max_in_flight.clone();
+
+            move |_: &Path, _: &ReceiptFile| {
+                let current = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                max_in_flight.fetch_max(current, Ordering::SeqCst);
+
+                // Hold the check until another one starts so sequential verification cannot pass by timing
+                let deadline = Instant::now() + Duration::from_secs(5);
+                while max_in_flight.load(Ordering::SeqCst) < 2 && Instant::now() < deadline {
+                    thread::sleep(Duration::from_millis(1));
+                }
+
+                in_flight.fetch_sub(1, Ordering::SeqCst);
+                FileState::Valid
+            }
+        };
+
+        let report = verify_files_with(dir.path().to_path_buf(), files, 4, verify).await.unwrap();
+
+        assert_eq!(report.files.len(), 8);
+        assert!(report.files.iter().all(|verified| verified.state == FileState::Valid));
+        assert!(max_in_flight.load(Ordering::SeqCst) > 1);
+        assert!(max_in_flight.load(Ordering::SeqCst) <= 4);
+    }
+
+    #[tokio::test]
+    async fn report_modified_config_separately() {
+        let dir = tempfile::tempdir().unwrap();
+
+        // sha1 of "data"
+        let file = |file_type, name: &str| ReceiptFile {
+            file_type,
+            path: String::new(),
+            name: name.into(),
+            sha1: "a17c9aaa61e80a1bf71d0d850af4e5baa9800bbd".into(),
+            size: Some(4),
+            extracted: false,
+        };
+
+        fs::write(dir.path().join("valid.jar"), "data").unwrap();
+        fs::write(dir.path().join("corrupt.jar"), "edit").unwrap();
+        fs::write(dir.path().join("modified.cfg"), "edit").unwrap();
+        fs::write(dir.path().join("extracted.cfg"), "edit").unwrap();
+
+        let files = vec![
+            file(PackFileType::Mod, "valid.jar"),
+            file(PackFileType::Mod, "corrupt.jar"),
+            file(PackFileType::Mod, "missing.jar"),
+            file(PackFileType::Config, "modified.cfg"),
+            ReceiptFile { extracted: true, ..file(PackFileType::Config, "extracted.cfg") },
+        ];
+
+        let report = verify_files(dir.path().to_path_buf(), files, 2).await.unwrap();
+
+        let state = |name: &str| report.files.iter().find(|verified| verified.file.name == name).unwrap().state;
+
+        assert_eq!(state("valid.jar"), FileState::Valid);
+        assert_eq!(state("corrupt.jar"), FileState::Corrupt);
+        assert_eq!(state("missing.jar"), FileState::Missing);
+        assert_eq!(state("modified.cfg"), FileState::Modified);
+        assert_eq!(state("extracted.cfg"), FileState::Modified);
+
+        assert_eq!(report.bad().count(), 2);
+        assert_eq!(report.modified().count(), 2);
+    }
+}
@@ -5,22 +5,42 @@
  */
 
 use futures::{Future, Stream, StreamExt};
-use tokio::fs;
+use sha1::{Digest, Sha1};
+use tokio::{
+    fs::{self, OpenOptions},
+    io::{AsyncWriteExt, BufWriter},
+    task::{self, JoinError},
+};
 use std::{
     error::Error,
     fmt::{self, Display, Formatter},
     fs::File,
-    io::{self, BufWriter, Write},
-    path::PathBuf,
+    io::{self, BufReader},
+    path::{Path, PathBuf},
     pin::Pin,
+    sync::Arc,
     task::{Context, Poll},
+    time::{Duration, Instant},
 };
 
-use crate::{api::modpack::{data::PackFile, download_stream::PackStreamItem}, util::file::check_file};
+use zip::result::ZipError;
+
+use crate::{api::modpack::data::{PackFile, PackFileType}, util::file::check_file};
+
+use super::{
+    source::{FileSource, StoreSource},
+    store::ContentStore,
+};
 
 pub struct FileInstalled {
     pub file: File,
     pub status: FileInstallStatus,
+
+    /// Bytes fetched from source. Resumed part of the file is not included.
+    pub bytes: u64,
+
+    /// Time took to install
+    pub duration: Duration,
 }
 
 #[derive(Debug)]
@@ -30,12 +50,43 @@ pub enum FileInstallStatus {
 
     /// Successfully installed
     Installed,
+
+    /// Installed from content store without downloading
+    FromStore,
 }
 
 #[derive(Debug)]
 pub enum FileInstallError {
     Reqwest(reqwest::Error),
     Io(io::Error),
+    Archive(ZipError),
+
+    /// File path is absolute or escapes install location. (path)
+    UnsafePath(String),
+
+    /// Downloaded file size is different. (expected, actual)
+    SizeMismatch(u64, u64),
+
+    /// Downloaded file sha1 hash is different. (expected, actual)
+    HashMismatch(String, String),
+}
+
+impl FileInstallError {
+    /// true if the error may not happen again on retry
+    pub fn is_transient(&self) -> bool {
+        match &self {
+            FileInstallError::Reqwest(err) => {
+                err.is_connect()
+                    || err.is_timeout()
+                    || err.is_body()
+                    || err.status().map(|status| status.is_server_error()).unwrap_or(false)
+            }
+
+            FileInstallError::SizeMismatch(_, _) | FileInstallError::HashMismatch(_, _) => true,
+
+            FileInstallError::Io(_) | FileInstallError::Archive(_) | FileInstallError::UnsafePath(_) => false,
+        }
+    }
 }
 
 impl Display for FileInstallError {
@@ -43,6 +94,10 @@ impl Display for FileInstallError {
         match &self {
             FileInstallError::Reqwest(err) => err.fmt(f),
             FileInstallError::Io(err) => err.fmt(f),
+            FileInstallError::Archive(err) => err.fmt(f),
+            FileInstallError::UnsafePath(path) => write!(f, "File path {} points outside of install location", path),
+            FileInstallError::SizeMismatch(expected, actual) => write!(f, "File size mismatch. expected: {} actual: {}", expected, actual),
+            FileInstallError::HashMismatch(expected, actual) => write!(f, "File sha1 mismatch. expected: {} actual: {}", expected, actual),
         }
     }
 }
@@ -59,57 +114,206 @@ impl From<reqwest::Error> for FileInstallError {
     }
 }
 
+impl From<ZipError> for FileInstallError {
+    fn from(err: ZipError) -> Self {
+        FileInstallError::Archive(err)
+    }
+}
+
+impl From<JoinError> for FileInstallError {
+    fn from(err: JoinError) -> Self {
+        FileInstallError::Io(io::Error::other(err))
+    }
+}
+
 impl Error for FileInstallError {}
 
-/// Pack install stream that uses PackDownloadStream
+/// Download retry policy
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// Maximum retry count after first attempt
+    pub max_retries: u32,
+
+    /// Delay before first retry. Doubles every retry.
+    pub base_delay: Duration,
+
+    /// Maximum delay between retries
+    pub max_delay: Duration,
+}
+
+impl RetryPolicy {
+    /// Delay before given retry (starting from 0)
+    pub fn delay(&self, retry: u32) -> Duration {
+        self.base_delay
+            .checked_mul(2_u32.saturating_pow(retry))
+            .unwrap_or(self.max_delay)
+            .min(self.max_delay)
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(10),
+        }
+    }
+}
+
+/// Checks if existing file has size and sha1 hash
+type FileCheck = Arc<dyn Fn(&Path, Option<u64>, &str) -> bool + Send + Sync>;
+
+/// Pack install stream installing files of file stream fetched from file source
 pub struct WebInstallStream<S> {
-    /// Download stream
+    /// Pack file stream
     stream: S,
 
+    /// Source file content is fetched from
+    source: Arc<dyn FileSource>,
+
     /// Download location
     location: PathBuf,
+
+    /// Download retry policy
+    retry: RetryPolicy,
+
+    /// Content store source files are installed from before fetching
+    store: Option<StoreSource>,
+
+    /// Existing file check run on blocking thread pool
+    check: FileCheck,
 }
 
 impl<S> WebInstallStream<S> {
-    pub fn new(stream: S, location: PathBuf) -> Self {
-        Self { stream, location }
+    pub fn new(stream: S, source: Arc<dyn FileSource>, location: PathBuf) -> Self {
+        Self::new_retry(stream, source, location, RetryPolicy::default())
+    }
+
+    /// Create install stream with custom retry policy
+    pub fn new_retry(stream: S, source: Arc<dyn FileSource>, location: PathBuf, retry: RetryPolicy) -> Self {
+        Self::new_store(stream, source, location, retry, None)
+    }
+
+    /// Create install stream with custom retry policy and content store.
+    /// Files in store are installed without fetching and fetched files are inserted to store.
+    pub fn new_store(
+        stream: S,
+        source: Arc<dyn FileSource>,
+        location: PathBuf,
+        retry: RetryPolicy,
+        store: Option<ContentStore>,
+    ) -> Self {
+        let store = store.map(StoreSource::new);
+
+        Self { stream, source, location, retry, store, check: Arc::new(check_file) }
+    }
+
+    /// Replace existing file check so tests can observe it
+    #[cfg(test)]
+    fn with_check(mut self, check: FileCheck) -> Self {
+        self.check = check;
+        self
     }
 }
 
-impl<S: Stream<Item = PackStreamItem> + Unpin> Stream for WebInstallStream<S> {
+impl<S: Stream<Item = PackFile> + Unpin> Stream for WebInstallStream<S> {
     type Item =
         Box<dyn Future<Output = (PackFile, Result<FileInstalled, FileInstallError>)> + Unpin + Send>;
 
     fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
         match self.stream.poll_next_unpin(cx) {
-            Poll::Ready(Some((file, response_fut))) => {
-                let location = self.location.clone();
-                let file_dir = location.join(&file.info.path);
-                let full_path = file_dir.join(&file.info.name);
-                let should_download = !check_file(&full_path, file.info.size, &file.info.sha1);
+            Poll::Ready(Some(file)) => {
+                let full_path = match file.info.install_path() {
+                    Some(install_path) => self.location.join(install_path),
+
+                    // Reject before fetching
+                    None => {
+                        let err = FileInstallError::UnsafePath(format!("{}/{}", file.info.path, file.info.name));
+
+                        return Poll::Ready(Some(Box::new(Box::pin(async { (file, Err(err)) }))));
+                    }
+                };
+
+                let file_dir = full_path.parent().map(Path::to_path_buf).unwrap_or_else(|| self.location.clone());
+                let part_path = file_dir.join(format!("{}.part", file.info.name));
+
+                let size = file.info.size;
+                let sha1 = file.info.sha1.clone();
+                let retry = self.retry;
+                let store = self.store.clone();
+                let source = self.source.clone();
+                let fetch_file = file.clone();
+                let check = self.check.clone();
+
+                // Only files never written after install are linked to store so modifying a file cannot corrupt the blob
+                let copy_only = !matches!(file.file_type, PackFileType::Mod | PackFileType::Overrides);
 
                 let fut = async move {
-                    // Move request future so it can be dropped when it didnt used.
-                    let response_fut = response_fut;
+                    let start = Instant::now();
+
+                    // Hash existing file on blocking thread pool
+                    let should_download = {
+                        let (full_path, sha1) = (full_path.clone(), sha1.clone());
+
+                        !task::spawn_blocking(move || check(&full_path, size, &sha1)).await?
+                    };
 
                     if should_download {
-                        let mut res = response_fut.await?;
                         fs::create_dir_all(file_dir).await?;
 
-                        let mut out_file = File::create(full_path)?;
-                        {
-                            let mut writer = BufWriter::new(&mut out_file);
+                        // Blob modified through linked file is removed and fetched again
+                        if let Some(store) = &store {
+                            if store.install(&fetch_file, &full_path, copy_only).await? {
+                                let file = fs::File::open(full_path).await?.into_std().await;
+                                return Ok(FileInstalled {
+                                    status: FileInstallStatus::FromStore,
+                                    file,
+                                    bytes: 0,
+                                    duration: start.elapsed(),
+                                });
+                            }
+                        }
+
+                        let mut retries = 0;
+                        let mut bytes = 0;
+                        loop {
+                            match download_file(source.as_ref(), &fetch_file, &part_path, &mut bytes).await {
+                                Ok(_) => break,
+
+                                Err(err) if err.is_transient() && retries < retry.max_retries => {
+                                    tokio::time::sleep(retry.delay(retries)).await;
+                                    retries += 1;
+                                }
 
-                            while let Some(chunk) = res.chunk().await? {
-                                writer.write_all(&chunk)?;
+                                Err(err) => return Err(err),
                             }
-                            writer.flush()?;
                         }
 
-                        Ok(FileInstalled { status: FileInstallStatus::Installed, file: out_file })
+                        fs::rename(&part_path, &full_path).await?;
+
+                        // Store is best effort
+                        if let Some(store) = store {
+                            let (full_path, sha1) = (full_path.clone(), sha1.clone());
+
+                            task::spawn_blocking(move || store.store().insert(&sha1, &full_path, copy_only)).await?.ok();
+                        }
+
+                        let out_file = fs::File::open(full_path).await?.into_std().await;
+                        Ok(FileInstalled {
+                            status: FileInstallStatus::Installed,
+                            file: out_file,
+                            bytes,
+                            duration: start.elapsed(),
+                        })
                     } else {
-                        let file = File::open(full_path)?;
-                        Ok(FileInstalled { status: FileInstallStatus::ValidFileExists, file })
+                        let file = fs::File::open(full_path).await?.into_std().await;
+                        Ok(FileInstalled {
+                            status: FileInstallStatus::ValidFileExists,
+                            file,
+                            bytes: 0,
+                            duration: start.elapsed(),
+                        })
                     }
                 };
 
@@ -121,3 +325,306 @@ impl<S: Stream<Item = PackStreamItem> + Unpin> Stream for WebInstallStream<S> {
         }
     }
 }
+
+/// Fetch file from source to part_path and verify it. Fetched bytes are added to transferred.
+/// Existing part file is resumed if the source supports starting from offset.
+async fn download_file(
+    source: &dyn FileSource,
+    file: &PackFile,
+    part_path: &Path,
+    transferred: &mut u64,
+) -> Result<(), FileInstallError> {
+    let size = file.info.size;
+    let sha1 = &file.info.sha1;
+
+    let (mut written, mut hasher) = {
+        let part_path = part_path.to_path_buf();
+
+        task::spawn_blocking(move || {
+            let mut hasher = Sha1::new();
+            let written = resume_part(&part_path, size, &mut hasher)?;
+
+            Ok::<(u64, Sha1), FileInstallError>((written, hasher))
+        })
+        .await??
+    };
+
+    // Part file can be already complete if it was not renamed
+    if size.is_none_or(|size| written < size) {
+        let mut content = source.fetch(file, written).await?;
+
+        if content.offset != written {
+            // Source cannot start from offset. Fetch from start.
+            hasher = Sha1::new();
+            written = 0;
+        }
+
+        let mut writer = BufWriter::new(
+            OpenOptions::new()
+                .create(true)
+                .write(true)
+                .append(written > 0)
+                .truncate(written == 0)
+                .open(part_path)
+                .await?,
+        );
+
+        while let Some(chunk) = content.stream.next().await {
+            let chunk = chunk?;
+
+            hasher.update(&chunk);
+            written += chunk.len() as u64;
+            *transferred += chunk.len() as u64;
+
+            writer.write_all(&chunk).await?;
+        }
+        writer.flush().await?;
+    }
+
+    // Unknown size or hash cannot be verified
+    if let Some(size) = size.filter(|size| written != *size) {
+        fs::remove_file(part_path).await?;
+        return Err(FileInstallError::SizeMismatch(size, written));
+    }
+
+    let hash = hex::encode(hasher.finalize());
+    if !sha1.is_empty() && !hash.eq_ignore_ascii_case(sha1) {
+        fs::remove_file(part_path).await?;
+        return Err(FileInstallError::HashMismatch(sha1.clone(), hash));
+    }
+
+    Ok(())
+}
+
+/// Feed existing part file to hasher and returns its length.
+/// Part file larger than expected size is removed. Blocks current thread.
+fn resume_part(part_path: &Path, size: Option<u64>, hasher: &mut Sha1) -> Result<u64, FileInstallError> {
+    let len = match std::fs::metadata(part_path) {
+        Ok(meta) if meta.is_file() => meta.len(),
+        _ => return Ok(0),
+    };
+
+    if size.is_some_and(|size| len > size) {
+        std::fs::remove_file(part_path)?;
+        return Ok(0);
+    }
+
+    io::copy(&mut BufReader::new(File::open(part_path)?), hasher)?;
+
+    Ok(len)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        path::Path,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        thread,
+        time::{Duration, Instant},
+    };
+
+    use bytes::Bytes;
+    use futures::{stream, StreamExt};
+    use sha1::{Digest, Sha1};
+
+    use crate::{
+        api::modpack::data::{FileVersion, PackFile, PackFileInfo, PackFileType},
+        util::file::check_file,
+    };
+
+    use super::{
+        super::{source::MemorySource, store::ContentStore},
+        FileInstallError, FileInstallStatus, FileInstalled, RetryPolicy, WebInstallStream,
+    };
+
+    const CONTENT: &[u8] = b"mod file content";
+
+    fn pack_file(name: &str, content: &[u8]) -> PackFile {
+        PackFile {
+            file_type: PackFileType::Mod,
+            info: PackFileInfo {
+                id: 0,
+                name: name.into(),
+                optional: false,
+                path: "mods".into(),
+                clientonly: false,
+                serveronly: false,
+                sha1: hex::encode(Sha1::digest(content)),
+                size: Some(content.len() as u64),
+                updated: 0,
+                url: format!("memory://{}", name),
+                version: FileVersion::Numberic(0),
+            },
+        }
+    }
+
+    fn source(files: &[(&PackFile, &'static [u8])]) -> MemorySource {
+        let mut source = MemorySource::new();
+
+        for (file, content) in files {
+            source.insert(file.info.url.clone(), Bytes::from_static(content));
+        }
+
+        source
+    }
+
+    async fn install(
+        source: MemorySource,
+        location: &Path,
+        file: PackFile,
+        store: Option<ContentStore>,
+    ) -> Result<FileInstalled, FileInstallError> {
+        // Mismatch is not retried so failing test doesn't wait
+        let retry = RetryPolicy { max_retries: 0, base_delay: Duration::ZERO, max_delay: Duration::ZERO };
+
+        let mut stream =
+            WebInstallStream::new_store(stream::iter(vec![file]), Arc::new(source), location.to_path_buf(), retry, store)
+                .buffer_unordered(1);
+
+        let (_, res) = stream.next().await.unwrap();
+        assert!(stream.next().await.is_none());
+
+        res
+    }
+
+    #[tokio::test]
+    async fn install_file_from_source() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = pack_file("a.jar", CONTENT);
+
+        let installed = install(source(&[(&file, CONTENT)]), dir.path(), file, None).await.unwrap();
+
+        assert!(matches!(installed.status, FileInstallStatus::Installed));
+        assert_eq!(installed.bytes, CONTENT.len() as u64);
+        assert_eq!(fs::read(dir.path().join("mods").join("a.jar")).unwrap(), CONTENT);
+        assert!(!dir.path().join("mods").join("a.jar.part").exists());
+    }
+
+    #[tokio::test]
+    async fn skip_valid_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = pack_file("a.jar", CONTENT);
+
+        fs::create_dir_all(dir.path().join("mods")).unwrap();
+        fs::write(dir.path().join("mods").join("a.jar"), CONTENT).unwrap();
+
+        // Source without content fails if the file is fetched
+        let installed = install(MemorySource::new(), dir.path(), file, None).await.unwrap();
+
+        assert!(matches!(installed.status, FileInstallStatus::ValidFileExists));
+        assert_eq!(installed.bytes, 0);
+    }
+
+    #[tokio::test]
+    async fn reject_hash_mismatch() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = pack_file("a.jar", CONTENT);
+
+        let res = install(source(&[(&file, b"mod file CONTENT")]), dir.path(), file, None).await;
+
+        assert!(matches!(res, Err(FileInstallError::HashMismatch(_, _))));
+        assert!(!dir.path().join("mods").join("a.jar").exists());
+        assert!(!dir.path().join("mods").join("a.jar.part").exists());
+    }
+
+    #[tokio::test]
+    async fn resume_part_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = pack_file("a.jar", CONTENT);
+
+        fs::create_dir_all(dir.path().join("mods")).unwrap();
+        fs::write(dir.path().join("mods").join("a.jar.part"), &CONTENT[..4]).unwrap();
+
+        let installed = install(source(&[(&file, CONTENT)]), dir.path(), file, None).await.unwrap();
+
+        assert!(matches!(installed.status, FileInstallStatus::Installed));
+        assert_eq!(installed.bytes, (CONTENT.len() - 4) as u64);
+        assert_eq!(fs::read(dir.path().join("mods").join("a.jar")).unwrap(), CONTENT);
+    }
+
+    #[tokio::test]
+    async fn install_from_store() {
+        let dir = tempfile::tempdir().unwrap();
+        let store_dir = tempfile::tempdir().unwrap();
+        let store = ContentStore::new(store_dir.path().to_path_buf());
+        let file = pack_file("a.jar", CONTENT);
+
+        let stored = store_dir.path().join("a.jar");
+        fs::write(&stored, CONTENT).unwrap();
+        store.insert(&file.info.sha1, &stored, true).unwrap();
+
+        let installed = install(MemorySource::new(), dir.path(), file, Some(store)).await.unwrap();
+
+        assert!(matches!(installed.status, FileInstallStatus::FromStore));
+        assert_eq!(fs::read(dir.path().join("mods").join("a.jar")).unwrap(), CONTENT);
+    }
+
+    #[tokio::test]
+    async fn download_again_if_store_blob_modified() {
+        let dir = tempfile::tempdir().unwrap();
+        let store_dir = tempfile::tempdir().unwrap();
+        let store = ContentStore::new(store_dir.path().to_path_buf());
+        let file = pack_file("a.jar", CONTENT);
+
+        // Blob with same size and different content
+        let blob_path = store.blob_path(&file.info.sha1).unwrap();
+        fs::create_dir_all(blob_path.parent().unwrap()).unwrap();
+        fs::write(&blob_path, b"mod file CONTENT").unwrap();
+
+        let installed = install(source(&[(&file, CONTENT)]), dir.path(), file, Some(store)).await.unwrap();
+
+        assert!(matches!(installed.status, FileInstallStatus::Installed));
+        assert_eq!(fs::read(dir.path().join("mods").join("a.jar")).unwrap(), CONTENT);
+        assert_eq!(fs::read(blob_path).unwrap(), CONTENT);
+    }
+
+    #[tokio::test]
+    async fn check_existing_files_concurrently() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let files = (0..8).map(|i| pack_file(&format!("{}.jar", i), CONTENT)).collect::<Vec<PackFile>>();
+        for file in &files {
+            install(source(&[(file, CONTENT)]), dir.path(), file.clone(), None).await.unwrap();
+        }
+
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let max_in_flight = Arc::new(AtomicUsize::new(0));
+
+        let check = {
+            let in_flight = in_flight.clone();
+            let max_in_flight = max_in_flight.clone();
+
+            move |path: &Path, size: Option<u64>, sha1: &str| {
+                let current = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                max_in_flight.fetch_max(current, Ordering::SeqCst);
+
+                // Hold the check until another one starts. Checks on the single test runtime thread would never overlap.
+                let deadline = Instant::now() + Duration::from_secs(5);
+                while max_in_flight.load(Ordering::SeqCst) < 2 && Instant::now() < deadline {
+                    thread::sleep(Duration::from_millis(1));
+                }
+
+                let valid = check_file(path, size, sha1);
+                in_flight.fetch_sub(1, Ordering::SeqCst);
+
+                valid
+            }
+        };
+
+        let results = WebInstallStream::new(stream::iter(files), Arc::new(MemorySource::new()), dir.path().to_path_buf())
+            .with_check(Arc::new(check))
+            .buffer_unordered(4)
+            .collect::<Vec<_>>()
+            .await;
+
+        assert_eq!(results.len(), 8);
+        assert!(results
+            .iter()
+            .all(|(_, res)| matches!(res, Ok(installed) if matches!(installed.status, FileInstallStatus::ValidFileExists))));
+        assert!(max_in_flight.load(Ordering::SeqCst) > 1);
+    }
+}
@@ -9,7 +9,7 @@ use futures::{stream, StreamExt};
 use std::{error::Error, fmt::{Display, Formatter}, io};
 use tokio::task::JoinError;
 
-use crate::api::modpack::{APIResult, ModPackAPI, info::ModPack, search::SearchResult};
+use crate::api::modpack::{APIResult, ModPackAPI, ModPackApiError, info::ModPack, search::SearchResult};
 
 /// Modpack select screen
 pub struct ModpackSelect {
@@ -26,7 +26,7 @@ impl ModpackSelect {
     pub fn select(self, prompt: Option<&str>) -> Result<Option<ModPackVariant>, io::Error> {
         let list = self.list;
 
-        if list.len() < 1 {
+        if list.is_empty() {
             return Ok(None);
         }
 
@@ -65,10 +65,8 @@ impl ModPackVariant {
     pub fn display(&self) -> String {
         let mut str = String::new();
 
-        match &self {
-            ModPackVariant::CurseForge(_) => str.push_str("(curseforge) "),
-            
-            _ => {}
+        if let ModPackVariant::CurseForge(_) = &self {
+            str.push_str("(curseforge) ");
         }
 
         let info = self.info();
@@ -89,7 +87,7 @@ impl ModPackVariant {
 #[derive(Debug)]
 pub enum TaskError {
     Thread(JoinError),
-    Reqwest(reqwest::Error),
+    Api(ModPackApiError),
 }
 
 impl From<JoinError> for TaskError {
@@ -98,9 +96,9 @@ impl From<JoinError> for TaskError {
     }
 }
 
-impl From<reqwest::Error> for TaskError {
-    fn from(err: reqwest::Error) -> Self {
-        Self::Reqwest(err)
+impl From<ModPackApiError> for TaskError {
+    fn from(err: ModPackApiError) -> Self {
+        Self::Api(err)
     }
 }
 
@@ -108,7 +106,7 @@ impl Error for TaskError {
     fn source(&self) -> Option<&(dyn Error + 'static)> {
         match &self {
             TaskError::Thread(err) => Some(err),
-            TaskError::Reqwest(err) => Some(err)
+            TaskError::Api(err) => Some(err)
         }
     }
 }
@@ -117,29 +115,48 @@ impl Display for TaskError {
     fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
         match &self {
             TaskError::Thread(err) => err.fmt(f),
-            TaskError::Reqwest(err) => err.fmt(f)
+            TaskError::Api(err) => err.fmt(f)
         }
     }
 }
 
-/// Create ModPackVariant list from search result
+/// Create ModPackVariant list from search result fetching manifests concurrently
 pub async fn create_list_from_result(
+    api: &ModPackAPI,
     result: SearchResult,
+    concurrency: usize,
 ) -> Result<Vec<ModPackVariant>, TaskError> {
+    let concurrency = concurrency.max(1);
+
     let mut list: Vec<ModPackVariant> =
         Vec::with_capacity(result.packs.len() + result.curseforge.len());
 
-    let modpack_stream = stream::iter(result.packs).map(ModPackAPI::modpack_manifest);
-    let curseforge_stream = stream::iter(result.curseforge).map(ModPackAPI::curseforge_manifest);
+    let modpack_stream = {
+        let api = api.clone();
+
+        stream::iter(result.packs).map(move |id| {
+            let api = api.clone();
+            async move { api.modpack_manifest(id).await }
+        })
+    };
+
+    let curseforge_stream = {
+        let api = api.clone();
+
+        stream::iter(result.curseforge).map(move |id| {
+            let api = api.clone();
+            async move { api.curseforge_manifest(id).await }
+        })
+    };
 
     let modpack_task = tokio::spawn(
         modpack_stream
-            .buffer_unordered(16)
+            .buffer_unordered(concurrency)
             .collect::<Vec<APIResult<ModPack>>>(),
     );
     let curseforge_task = tokio::spawn(
         curseforge_stream
-            .buffer_unordered(16)
+            .buffer_unordered(concurrency)
             .collect::<Vec<APIResult<ModPack>>>(),
     );
 
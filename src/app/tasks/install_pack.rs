@@ -5,44 +5,97 @@
  */
 
 use std::{
+    collections::HashMap,
+    fs::File,
     io::BufReader,
-    path::{Path, PathBuf}
+    time::Duration,
+    path::{Path, PathBuf},
+    sync::Arc,
 };
 
-use futures::StreamExt;
+use futures::{stream, StreamExt};
 use humansize::{file_size_opts, FileSize};
 use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
 use tokio::task::JoinHandle;
 use zip::ZipArchive;
 
 use crate::{
-    api::modpack::{
-        data::{PackFile, PackFileType},
-        download_stream::PackDownloadStream,
-    },
+    api::modpack::data::{PackFile, PackFileType},
     app::{
         pack_install::{
             package::PackageInstaller,
+            receipt::{InstallReceipt, ReceiptFile},
+            report::{FileInstallOutcome, InstallReport},
+            source::FileSource,
+            update::ConfigAction,
             web::{FileInstallStatus, WebInstallStream},
         },
+        config::AppConfig,
+        pack_select::TaskError,
         AppError,
     },
 };
 
-/// Spawn pack install task using files and install location.
+/// Spawn pack install task installing files fetched from source to install location.
+/// Modified files extracted by previous install are handled by extract_actions and kept if not listed.
+/// Overrides package files are only downloaded without extracting if extract_actions is None.
+/// Progress is hidden if multi is None. Returns install report of every file.
 pub fn spawn_install_task(
+    source: Arc<dyn FileSource>,
     files: Vec<PackFile>,
     install_location: PathBuf,
-    multi: &MultiProgress,
-) -> JoinHandle<Result<(), AppError>> {
-    let total = multi.add(ProgressBar::new(files.len() as u64));
+    extract_actions: Option<HashMap<PathBuf, ConfigAction>>,
+    config: &AppConfig,
+    multi: Option<&MultiProgress>,
+) -> JoinHandle<Result<InstallReport, AppError>> {
+    // Bars of hidden MultiProgress panic on println so standalone hidden bars are used instead
+    let add_bar = |len: u64| match multi {
+        Some(multi) => multi.add(ProgressBar::new(len)),
+        None => ProgressBar::hidden(),
+    };
+
+    let total = add_bar(files.len() as u64);
+
+    // Progress bars should be added before the task starts so MultiProgress can wait them.
+    let mut extract_bars = match extract_actions {
+        Some(_) => files
+            .iter()
+            .filter(|file| matches!(file.file_type, PackFileType::Overrides))
+            .map(|_| add_bar(0))
+            .collect::<Vec<ProgressBar>>(),
+
+        None => Vec::new(),
+    };
+
+    let file_stream = stream::iter(files);
+    let retry = config.retry_policy();
+    let concurrency = config.download_concurrency.max(1);
+    let store = config.content_store();
+
+    // Files extracted by previous install are needed to keep user modified overrides
+    let previous = Arc::new(
+        InstallReceipt::load(&install_location)
+            .map(|receipt| receipt.files)
+            .unwrap_or_default(),
+    );
+    let extract_actions = extract_actions.map(Arc::new);
+
     tokio::spawn(async move {
         total.set_style(ProgressStyle::default_bar().template(
             "{spinner:.green} [{elapsed_precise}] [{wide_bar:.white/gray}] {pos} / {len} ({eta})",
         ));
 
-        let mut stream = WebInstallStream::new(PackDownloadStream::new(files), install_location)
-            .buffer_unordered(60);
+        for bar in &extract_bars {
+            bar.set_style(ProgressStyle::default_bar().template(
+                "{spinner:.blue} [{bar:40.cyan/blue}] {pos} / {len} {wide_msg}",
+            ));
+        }
+
+        let mut stream = WebInstallStream::new_store(file_stream, source, install_location.clone(), retry, store)
+            .buffer_unordered(concurrency);
+
+        let mut report = InstallReport::new();
+        let mut extract_tasks = Vec::new();
 
         while let Some((file, res)) = stream.next().await {
             match res {
@@ -50,7 +103,7 @@ pub fn spawn_install_task(
                     match &result.status {
                         FileInstallStatus::ValidFileExists => {
                             total.println(
-                                &console::style(format!(
+                                console::style(format!(
                                     "{} already installed. Skipping...",
                                     &file.info.name
                                 ))
@@ -59,39 +112,124 @@ pub fn spawn_install_task(
                             );
                         }
 
+                        FileInstallStatus::FromStore => {
+                            total.println(
+                                console::style(format!(
+                                    "{} found in store. Linking...",
+                                    &file.info.name
+                                ))
+                                .green()
+                                .to_string(),
+                            );
+                        }
+
                         FileInstallStatus::Installed => {}
                     }
 
-                    // Handle overrides.zip
-                    if let PackFileType::Overrides = &file.file_type {
-                        let archive = ZipArchive::new(BufReader::new(result.file))?;
+                    total.println(format_file_info(&file));
+
+                    let outcome = FileInstallOutcome::from(&result.status);
 
-                        let installer = PackageInstaller::new(archive);
+                    // Handle overrides.zip on separate task so downloads are not stalled while extracting
+                    if let (PackFileType::Overrides, Some(extract_actions)) = (&file.file_type, &extract_actions) {
+                        let progress = extract_bars.pop().unwrap_or_else(ProgressBar::hidden);
 
-                        total.println("[warn] Package install is not yet implemented");
+                        let task = spawn_extract_task(
+                            result.file,
+                            install_location.clone(),
+                            previous.clone(),
+                            extract_actions.clone(),
+                            progress,
+                        );
+                        extract_tasks.push((file, outcome, result.bytes, result.duration, task));
+
+                        continue;
                     }
 
-                    total.println(format_file_info(&file));
+                    report.push(file, outcome, result.bytes, result.duration);
                 }
 
                 Err(err) => {
                     total.println(
-                        &console::style(format!(
+                        console::style(format!(
                             "Error occured while downloading {}. err: {}",
                             &file.info.name, err
                         ))
                         .red()
                         .to_string(),
                     );
+
+                    report.push(file, FileInstallOutcome::Failed(err.to_string()), 0, Duration::from_secs(0));
+                }
+            }
+
+            total.inc(1);
+        }
+
+        for (file, outcome, bytes, duration, task) in extract_tasks {
+            let extract_res = match task.await {
+                Ok(res) => res,
+                Err(err) => Err(TaskError::from(err).into()),
+            };
+
+            match extract_res {
+                Ok(extracted) => report.push_extracted(file, outcome, bytes, duration, extracted),
+
+                Err(err) => {
+                    total.println(
+                        console::style(format!("Error occured while extracting {}. err: {}", &file.info.name, err))
+                            .red()
+                            .to_string(),
+                    );
+
+                    report.push(file, FileInstallOutcome::Failed(err.to_string()), bytes, duration);
                 }
             }
 
             total.inc(1);
         }
 
+        for bar in extract_bars {
+            bar.finish_and_clear();
+        }
+
         total.finish();
 
-        Ok(())
+        if !report.is_complete() {
+            total.println(
+                console::style(format!("{} files failed to install", report.failed().count()))
+                    .red()
+                    .bold()
+                    .to_string(),
+            );
+
+            for file_report in report.failed() {
+                if let FileInstallOutcome::Failed(err) = &file_report.outcome {
+                    total.println(format!("{} {}", format_file_info(&file_report.file), console::style(err).red()));
+                }
+            }
+        }
+
+        Ok(report)
+    })
+}
+
+/// Spawn task extracting overrides package file to install location on blocking thread.
+/// Returns extracted files.
+fn spawn_extract_task(
+    file: File,
+    install_location: PathBuf,
+    previous: Arc<Vec<ReceiptFile>>,
+    actions: Arc<HashMap<PathBuf, ConfigAction>>,
+    progress: ProgressBar,
+) -> JoinHandle<Result<Vec<ReceiptFile>, AppError>> {
+    tokio::task::spawn_blocking(move || {
+        let mut installer = PackageInstaller::new(ZipArchive::new(BufReader::new(file))?);
+
+        let res = installer.extract_overrides(&install_location, &previous, &actions, &progress);
+        progress.finish_and_clear();
+
+        Ok(res?)
     })
 }
 
@@ -127,10 +265,9 @@ fn format_file_info(file: &PackFile) -> String {
         "{} {} - {}",
         prefix,
         full_name,
-        if info.size > 0 {
-            info.size.file_size(file_size_opts::BINARY).unwrap()
-        } else {
-            "unknown".into()
+        match info.size {
+            Some(size) => size.file_size(file_size_opts::BINARY).unwrap(),
+            None => "unknown".into(),
         }
     )
 }
@@ -26,16 +26,11 @@ impl PackVersionSelect {
     pub fn select(self, prompt: Option<&str>) -> Result<Option<PackVersion>, io::Error> {
         let mut list = self.list;
 
-        if list.len() < 1 {
+        if list.is_empty() {
             return Ok(None);
         }
 
-        list.sort_by(|ver1, ver2| {
-            let ver1 = Version::parse(&ver1.name).unwrap_or(Version::new(1, 0, 0));
-            let ver2 = Version::parse(&ver2.name).unwrap_or(Version::new(1, 0, 0));
-
-            ver1.cmp(&ver2).reverse()
-        });
+        sort_versions(&mut list);
 
         let index = {
             let mut sel = Select::new();
@@ -52,8 +47,18 @@ impl PackVersionSelect {
     }
 
     /// Create PackVersion selection description
-    fn version_desc(version: &PackVersion) -> String {
+    pub fn version_desc(version: &PackVersion) -> String {
         format!("{} - {} {}", version.id, version.version_type, version.name)
     }
 
 }
+
+/// Sort versions from latest to oldest by semantic version
+pub fn sort_versions(list: &mut [PackVersion]) {
+    list.sort_by(|ver1, ver2| {
+        let ver1 = Version::parse(&ver1.name).unwrap_or_else(|_| Version::new(1, 0, 0));
+        let ver2 = Version::parse(&ver2.name).unwrap_or_else(|_| Version::new(1, 0, 0));
+
+        ver1.cmp(&ver2).reverse()
+    });
+}
@@ -0,0 +1,421 @@
+/*
+ * Created on Sat May 22 2021
+ *
+ * Copyright (c) storycraft. Licensed under the GNU General Public License v3.
+ */
+
+use std::path::PathBuf;
+
+use clap::{crate_description, crate_version, App, Arg, ArgMatches, SubCommand};
+use modpack_installer::app::{commands::{ExportOptions, InstallOptions, UninstallOptions, UpdateOptions}, config::AppConfig, AppError};
+
+/// Create command line app
+pub fn app() -> App<'static, 'static> {
+    App::new("modpack-installer")
+        .version(crate_version!())
+        .about(crate_description!())
+        .arg(
+            Arg::with_name("package")
+                .help("Modpack zip to install")
+                .index(1),
+        )
+        .arg(
+            Arg::with_name("json")
+                .global(true)
+                .long("json")
+                .help("Print machine readable json output without prompts and progress. Implies --yes"),
+        )
+        .arg(
+            Arg::with_name("dry-run")
+                .global(true)
+                .long("dry-run")
+                .help("Print files to download, valid files and extra files in install location then exit without installing"),
+        )
+        .arg(
+            Arg::with_name("offline")
+                .global(true)
+                .long("offline")
+                .help("Use cached api responses only. Fails if a response is not cached"),
+        )
+        .arg(
+            Arg::with_name("store")
+                .global(true)
+                .long("store")
+                .help("Share installed files between instances using content store"),
+        )
+        .arg(
+            Arg::with_name("config")
+                .global(true)
+                .long("config")
+                .value_name("FILE")
+                .help("Config file to use")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("concurrency")
+                .global(true)
+                .short("j")
+                .long("concurrency")
+                .value_name("N")
+                .help("Maximum concurrent file downloads")
+                .takes_value(true)
+                .validator(validate_count),
+        )
+        .arg(
+            Arg::with_name("fetch-concurrency")
+                .global(true)
+                .long("fetch-concurrency")
+                .value_name("N")
+                .help("Maximum concurrent api manifest fetches")
+                .takes_value(true)
+                .validator(validate_count),
+        )
+        .arg(
+            Arg::with_name("limit-rate")
+                .global(true)
+                .long("limit-rate")
+                .value_name("RATE")
+                .help("Total download bandwidth limit in byte/s. Accepts K, M, G suffix")
+                .takes_value(true)
+                .validator(|rate| parse_rate(&rate).map(|_| ())),
+        )
+        .arg(
+            Arg::with_name("retries")
+                .global(true)
+                .long("retries")
+                .value_name("N")
+                .help("Maximum retry count of failed download")
+                .takes_value(true)
+                .validator(|count| count.parse::<u32>().map(|_| ()).map_err(|err| err.to_string())),
+        )
+        .subcommand(
+            SubCommand::with_name("search")
+                .about("Search modpacks")
+                .arg(
+                    Arg::with_name("term")
+                        .help("Search term")
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("limit")
+                        .long("limit")
+                        .value_name("N")
+                        .help("Maximum search results")
+                        .takes_value(true)
+                        .default_value("50")
+                        .validator(validate_count),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("info")
+                .about("Show modpack information")
+                .arg(pack_id_arg())
+                .arg(curseforge_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("versions")
+                .about("List modpack versions from latest")
+                .arg(pack_id_arg())
+                .arg(curseforge_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("install")
+                .about("Install modpack without interaction")
+                .arg(pack_id_arg().required_unless("from-bundle"))
+                .arg(curseforge_arg())
+                .arg(
+                    Arg::with_name("version")
+                        .long("version")
+                        .value_name("VERSION")
+                        .help("Version id or name to install. Installs latest version if not set")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("from-bundle")
+                        .long("from-bundle")
+                        .value_name("FILE")
+                        .help("Install from bundle created by export-bundle without network")
+                        .takes_value(true)
+                        .conflicts_with_all(&["pack-id", "curseforge", "version"]),
+                )
+                .arg(
+                    Arg::with_name("dir")
+                        .long("dir")
+                        .value_name("DIR")
+                        .help("Install directory. Defaults to modpacks/<pack name> in minecraft directory")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("mc-dir")
+                        .long("mc-dir")
+                        .value_name("DIR")
+                        .help("Minecraft data directory")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("optional")
+                        .long("optional")
+                        .help("Install optional files"),
+                )
+                .arg(
+                    Arg::with_name("yes")
+                        .short("y")
+                        .long("yes")
+                        .help("Do not ask anything. Fails instead of asking when required files failed to install"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("export-bundle")
+                .about("Download modpack files into single bundle for offline install")
+                .arg(pack_id_arg())
+                .arg(curseforge_arg())
+                .arg(
+                    Arg::with_name("version")
+                        .long("version")
+                        .value_name("VERSION")
+                        .help("Version id or name to export. Exports latest version if not set")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("optional")
+                        .long("optional")
+                        .help("Include optional files"),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .short("o")
+                        .long("output")
+                        .value_name("FILE")
+                        .help("Bundle file to write")
+                        .takes_value(true)
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("update")
+                .about("Update installed modpack downloading changed files only")
+                .arg(
+                    Arg::with_name("dir")
+                        .help("Install location of modpack")
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("version")
+                        .long("version")
+                        .value_name("VERSION")
+                        .help("Version id or name to update to. Updates to latest version if not set")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("mc-dir")
+                        .long("mc-dir")
+                        .value_name("DIR")
+                        .help("Minecraft data directory")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("optional")
+                        .long("optional")
+                        .help("Install optional files not installed previously"),
+                )
+                .arg(
+                    Arg::with_name("config-policy")
+                        .long("config-policy")
+                        .value_name("POLICY")
+                        .help("Action for config files modified since installed. Asks for each file if not set, keeps them with --yes")
+                        .takes_value(true)
+                        .possible_values(&["keep", "overwrite", "backup"]),
+                )
+                .arg(
+                    Arg::with_name("yes")
+                        .short("y")
+                        .long("yes")
+                        .help("Do not ask anything. Fails instead of asking when required files failed to install"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("uninstall")
+                .about("Delete installed modpack files and its launcher profile")
+                .arg(
+                    Arg::with_name("dir")
+                        .help("Install location of modpack")
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("mc-dir")
+                        .long("mc-dir")
+                        .value_name("DIR")
+                        .help("Minecraft data directory")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("all")
+                        .long("all")
+                        .help("Delete whole install location. Saves are kept unless --force is given"),
+                )
+                .arg(
+                    Arg::with_name("force")
+                        .long("force")
+                        .requires("all")
+                        .help("Delete saves too"),
+                )
+                .arg(
+                    Arg::with_name("yes")
+                        .short("y")
+                        .long("yes")
+                        .help("Do not ask anything"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("verify")
+                .about("Check installed modpack files and report missing, corrupt and extra files")
+                .arg(
+                    Arg::with_name("dir")
+                        .help("Install location of modpack")
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("repair")
+                        .long("repair")
+                        .help("Download missing and corrupt files again"),
+                )
+                .arg(
+                    Arg::with_name("overwrite-modified")
+                        .long("overwrite-modified")
+                        .requires("repair")
+                        .help("Also replace config files edited since installed on repair"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("gc")
+                .about("Remove content store files not used by any installed modpack"),
+        )
+}
+
+fn pack_id_arg() -> Arg<'static, 'static> {
+    Arg::with_name("pack-id")
+        .help("Modpack id")
+        .required(true)
+        .validator(|id| id.parse::<u32>().map(|_| ()).map_err(|err| err.to_string()))
+}
+
+fn curseforge_arg() -> Arg<'static, 'static> {
+    Arg::with_name("curseforge")
+        .long("curseforge")
+        .help("Use curseforge pack id")
+}
+
+/// Create install options from install command matches
+pub fn install_options(matches: &ArgMatches, json: bool) -> InstallOptions {
+    InstallOptions {
+        pack_id: matches.value_of("pack-id").map_or(0, |id| id.parse().unwrap()),
+        curseforge: matches.is_present("curseforge"),
+        version: matches.value_of("version").map(String::from),
+        dir: matches.value_of("dir").map(PathBuf::from),
+        mc_dir: matches.value_of("mc-dir").map(PathBuf::from),
+        optional: matches.is_present("optional"),
+        assume_yes: matches.is_present("yes"),
+        json,
+        dry_run: matches.is_present("dry-run"),
+        bundle: matches.value_of("from-bundle").map(PathBuf::from),
+    }
+}
+
+/// Create export options from export-bundle command matches
+pub fn export_options(matches: &ArgMatches, json: bool) -> ExportOptions {
+    ExportOptions {
+        pack_id: matches.value_of("pack-id").unwrap().parse().unwrap(),
+        curseforge: matches.is_present("curseforge"),
+        version: matches.value_of("version").map(String::from),
+        optional: matches.is_present("optional"),
+        output: PathBuf::from(matches.value_of("output").unwrap()),
+        json,
+    }
+}
+
+/// Create update options from update command matches
+pub fn update_options(matches: &ArgMatches, json: bool) -> UpdateOptions {
+    UpdateOptions {
+        dir: PathBuf::from(matches.value_of("dir").unwrap()),
+        version: matches.value_of("version").map(String::from),
+        mc_dir: matches.value_of("mc-dir").map(PathBuf::from),
+        optional: matches.is_present("optional"),
+        assume_yes: matches.is_present("yes"),
+        json,
+        dry_run: matches.is_present("dry-run"),
+        config_action: matches.value_of("config-policy").map(|action| action.parse().unwrap()),
+    }
+}
+
+/// Create uninstall options from uninstall command matches
+pub fn uninstall_options(matches: &ArgMatches, json: bool) -> UninstallOptions {
+    UninstallOptions {
+        dir: PathBuf::from(matches.value_of("dir").unwrap()),
+        mc_dir: matches.value_of("mc-dir").map(PathBuf::from),
+        all: matches.is_present("all"),
+        force: matches.is_present("force"),
+        assume_yes: matches.is_present("yes"),
+        json,
+        dry_run: matches.is_present("dry-run"),
+    }
+}
+
+/// Load config file and apply command line options
+pub fn config(matches: &ArgMatches) -> Result<AppConfig, AppError> {
+    let mut config = match matches.value_of("config").map(PathBuf::from).or_else(AppConfig::default_path) {
+        Some(path) => AppConfig::load(&path)?,
+        None => AppConfig::default(),
+    };
+
+    if let Some(concurrency) = matches.value_of("concurrency") {
+        config.download_concurrency = concurrency.parse().unwrap();
+    }
+
+    if let Some(concurrency) = matches.value_of("fetch-concurrency") {
+        config.fetch_concurrency = concurrency.parse().unwrap();
+    }
+
+    if let Some(rate) = matches.value_of("limit-rate") {
+        config.bandwidth_limit = Some(parse_rate(rate).unwrap());
+    }
+
+    if let Some(retries) = matches.value_of("retries") {
+        config.max_retries = retries.parse().unwrap();
+    }
+
+    if matches.is_present("offline") {
+        config.offline = true;
+    }
+
+    if matches.is_present("store") {
+        config.store = true;
+    }
+
+    Ok(config)
+}
+
+fn validate_count(count: String) -> Result<(), String> {
+    match count.parse::<usize>() {
+        Ok(0) => Err("must be greater than 0".into()),
+        Ok(_) => Ok(()),
+        Err(err) => Err(err.to_string()),
+    }
+}
+
+/// Parse byte rate with optional binary K, M, G suffix
+fn parse_rate(rate: &str) -> Result<u64, String> {
+    let rate = rate.trim();
+
+    let (number, multiplier) = match rate.chars().last().map(|c| c.to_ascii_uppercase()) {
+        Some('K') => (&rate[..rate.len() - 1], 1024),
+        Some('M') => (&rate[..rate.len() - 1], 1024 * 1024),
+        Some('G') => (&rate[..rate.len() - 1], 1024 * 1024 * 1024),
+        _ => (rate, 1),
+    };
+
+    match number.parse::<u64>() {
+        Ok(0) => Err("must be greater than 0".into()),
+        Ok(number) => number.checked_mul(multiplier).ok_or_else(|| "too large".into()),
+        Err(err) => Err(err.to_string()),
+    }
+}
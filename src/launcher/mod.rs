@@ -10,7 +10,7 @@ use std::path::PathBuf;
 
 use directories::BaseDirs;
 
-pub const LAUNCHER_PROFILE_FILE: &'static str = "launcher_profiles.json";
+pub const LAUNCHER_PROFILE_FILE: &str = "launcher_profiles.json";
 
 /// Platform specific default minecraft dir
 pub fn default_minecraft_dir() -> PathBuf {
@@ -0,0 +1,10 @@
+/*
+ * Created on Fri May 21 2021
+ *
+ * Copyright (c) storycraft. Licensed under the GNU General Public License v3.
+ */
+
+pub mod api;
+pub mod launcher;
+pub mod app;
+pub mod util;
@@ -6,52 +6,188 @@
 
 use std::time::SystemTime;
 
-pub mod api;
-pub mod launcher;
-pub mod app;
-mod util;
+use modpack_installer::app::{self, commands, AppError};
+
+mod cli;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let start = SystemTime::now();
 
-    let args = std::env::args().map(|arg| arg.to_string()).collect::<Vec<String>>();
-    
-    match args.len() {
-        // Run default app
-        1 => {
-            match app::run().await {
-                Ok(_) => {
-                    let elapsed = start.elapsed()?;
-        
-                    println!(
-                        "{}",
-                        console::style(format!("Done. took {} ms", elapsed.as_millis())).green()
-                    );
-                },
-        
-                Err(err) => {
-                    println!(
-                        "{}",
-                        console::style(format!("Error while processing. err: {}", err)).red()
-                    );
-                }
+    let matches = cli::app().get_matches();
+    let json = matches.is_present("json");
+    let dry_run = matches.is_present("dry-run");
+
+    let config = match cli::config(&matches) {
+        Ok(config) => config,
+
+        Err(err) => {
+            print_error(Err(err), json);
+
+            std::process::exit(1);
+        }
+    };
+
+    let api = match config.api() {
+        Ok(api) => api,
+
+        Err(err) => {
+            print_error(Err(err), json);
+
+            std::process::exit(1);
+        }
+    };
+
+    let success = match matches.subcommand() {
+        ("search", Some(sub)) => {
+            let limit = sub.value_of("limit").unwrap().parse().unwrap();
+
+            print_error(commands::search(&api, sub.value_of("term").unwrap(), limit, &config, json).await, json)
+        }
+
+        ("info", Some(sub)) => {
+            let pack_id = sub.value_of("pack-id").unwrap().parse().unwrap();
+
+            print_error(commands::info(&api, pack_id, sub.is_present("curseforge"), json).await, json)
+        }
+
+        ("versions", Some(sub)) => {
+            let pack_id = sub.value_of("pack-id").unwrap().parse().unwrap();
+
+            print_error(commands::versions(&api, pack_id, sub.is_present("curseforge"), json).await, json)
+        }
+
+        ("install", Some(sub)) => {
+            let res = commands::install(&api, cli::install_options(sub, json), &config).await;
+
+            if json {
+                print_error(res, json)
+            } else {
+                print_result(start, res)?
             }
+        }
 
-            console::Term::stdout().read_key()?;
+        ("export-bundle", Some(sub)) => {
+            let res = commands::export_bundle(&api, cli::export_options(sub, json), &config).await;
+
+            if json {
+                print_error(res, json)
+            } else {
+                print_result(start, res)?
+            }
+        }
+
+        ("update", Some(sub)) => {
+            let res = commands::update(&api, cli::update_options(sub, json), &config).await;
+
+            if json {
+                print_error(res, json)
+            } else {
+                print_result(start, res)?
+            }
+        }
+
+        ("uninstall", Some(sub)) => {
+            let res = commands::uninstall(cli::uninstall_options(sub, json)).await;
+
+            if json {
+                print_error(res, json)
+            } else {
+                print_result(start, res)?
+            }
+        }
+
+        ("verify", Some(sub)) => {
+            let dir = sub.value_of("dir").unwrap().into();
+            let res = commands::verify(
+                &api,
+                dir,
+                sub.is_present("repair"),
+                sub.is_present("overwrite-modified"),
+                &config,
+                json,
+            )
+            .await;
+
+            if json {
+                print_error(res, json)
+            } else {
+                print_result(start, res)?
+            }
         }
 
-        // Run package installer
-        2 => {
-            println!("Package installer is not implemented yet");
-            todo!();
+        ("gc", Some(_)) => {
+            let res = commands::gc(&config, dry_run, json).await;
+
+            if json {
+                print_error(res, json)
+            } else {
+                print_result(start, res)?
+            }
         }
 
-        // Show description and helpmap
+        // Interactive installer asks questions and waits for key press
+        _ if json => print_error(Err(AppError::JsonUnsupported), json),
+
         _ => {
-            println!("Usage: {} [modpack zip to install]", args.get(0).unwrap_or(&"modpack-installer".into()));
+            let res = match matches.value_of("package") {
+                // Run package installer
+                Some(package) => app::run_package(package.into(), &config, dry_run).await,
+
+                // Run default app
+                None => app::run(&api, &config, dry_run).await,
+            };
+
+            let success = print_result(start, res)?;
+
+            console::Term::stdout().read_key()?;
+
+            success
         }
+    };
+
+    if !success {
+        std::process::exit(1);
     }
 
     Ok(())
 }
+
+/// Print error if the command failed. Returns true if the command succeeded.
+/// Json error is printed to stderr so stdout only contains command output.
+fn print_error(res: Result<(), AppError>, json: bool) -> bool {
+    match res {
+        Ok(_) => true,
+
+        Err(err) if json => {
+            eprintln!("{}", serde_json::json!({ "error": err.to_string().trim_end() }));
+
+            false
+        }
+
+        Err(err) => {
+            println!(
+                "{}",
+                console::style(format!("Error while processing. err: {}", err)).red()
+            );
+
+            false
+        }
+    }
+}
+
+/// Print app result with elapsed time. Returns true if the app succeeded.
+fn print_result(start: SystemTime, res: Result<(), AppError>) -> Result<bool, Box<dyn std::error::Error>> {
+    if !print_error(res, false) {
+        return Ok(false);
+    }
+
+    let elapsed = start.elapsed()?;
+
+    println!(
+        "{}",
+        console::style(format!("Done. took {} ms", elapsed.as_millis())).green()
+    );
+
+    Ok(true)
+}
@@ -0,0 +1,72 @@
+/*
+ * Created on Sat May 22 2021
+ *
+ * Copyright (c) storycraft. Licensed under the GNU General Public License v3.
+ */
+
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// Token bucket shared between downloads to limit total bandwidth
+#[derive(Debug, Clone)]
+pub struct BandwidthLimiter {
+    bucket: Arc<Mutex<Bucket>>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    /// Bytes per second
+    rate: u64,
+
+    /// Available bytes. Goes negative when consumed more than available.
+    tokens: f64,
+
+    /// Last refill time
+    last: Instant,
+}
+
+impl BandwidthLimiter {
+    /// Create limiter with bytes per second rate
+    pub fn new(rate: u64) -> Self {
+        let rate = rate.max(1);
+
+        Self {
+            bucket: Arc::new(Mutex::new(Bucket {
+                rate,
+                tokens: rate as f64,
+                last: Instant::now(),
+            })),
+        }
+    }
+
+    /// Bytes per second rate
+    pub fn rate(&self) -> u64 {
+        self.bucket.lock().unwrap().rate
+    }
+
+    /// Consume bytes and wait until the bucket is refilled
+    pub async fn consume(&self, bytes: u64) {
+        let wait = {
+            let mut bucket = self.bucket.lock().unwrap();
+
+            let now = Instant::now();
+            let refill = now.duration_since(bucket.last).as_secs_f64() * bucket.rate as f64;
+
+            // Allows one second burst at most
+            bucket.tokens = (bucket.tokens + refill).min(bucket.rate as f64) - bytes as f64;
+            bucket.last = now;
+
+            if bucket.tokens < 0.0 {
+                Duration::from_secs_f64(-bucket.tokens / bucket.rate as f64)
+            } else {
+                Duration::from_secs(0)
+            }
+        };
+
+        if wait > Duration::from_secs(0) {
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
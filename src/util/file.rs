@@ -4,17 +4,19 @@
  * Copyright (c) storycraft. Licensed under the GNU General Public License v3.
  */
 
-use std::{io::BufReader, path::PathBuf, fs};
+use std::{io::BufReader, path::Path, fs};
 
 use super::hash::validate_data;
 
-/// Check if file is valid
-pub fn check_file(path: &PathBuf, size: i64, sha1: &str) -> bool {
-    if let Ok(meta) = fs::metadata(&path) {
-        if !meta.is_file() || size < 0 || meta.len() != size as u64 {
+/// Check if file is valid. Unknown size or empty sha1 is not checked.
+pub fn check_file(path: &Path, size: Option<u64>, sha1: &str) -> bool {
+    if let Ok(meta) = fs::metadata(path) {
+        if !meta.is_file() || size.is_some_and(|size| meta.len() != size) {
             false
+        } else if sha1.is_empty() {
+            true
         } else {
-            if let Ok(reader) = fs::File::open(&path) {
+            if let Ok(reader) = fs::File::open(path) {
                 let mut reader = BufReader::new(reader);
 
                 validate_data(sha1, &mut reader)
@@ -26,3 +28,38 @@ pub fn check_file(path: &PathBuf, size: i64, sha1: &str) -> bool {
         false
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::check_file;
+
+    /// sha1 of "data"
+    const DATA_SHA1: &str = "a17c9aaa61e80a1bf71d0d850af4e5baa9800bbd";
+
+    #[test]
+    fn check_known_size_and_hash() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("file");
+        fs::write(&path, "data").unwrap();
+
+        assert!(check_file(&path, Some(4), DATA_SHA1));
+        assert!(!check_file(&path, Some(5), DATA_SHA1));
+        assert!(!check_file(&path, Some(4), "0000000000000000000000000000000000000000"));
+        assert!(!check_file(&dir.path().join("missing"), Some(4), DATA_SHA1));
+    }
+
+    #[test]
+    fn skip_unknown_size_and_hash() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("file");
+        fs::write(&path, "data").unwrap();
+
+        assert!(check_file(&path, None, DATA_SHA1));
+        assert!(check_file(&path, Some(4), ""));
+        assert!(check_file(&path, None, ""));
+        assert!(!check_file(&path, Some(5), ""));
+        assert!(!check_file(dir.path(), None, ""));
+    }
+}
@@ -4,14 +4,14 @@
  * Copyright (c) storycraft. Licensed under the GNU General Public License v3.
  */
 
-use std::io::{self, BufWriter, Read};
+use std::io::{self, BufWriter, Read, Write};
 use sha1::{Sha1, Digest};
 
 /// Check if the reader data is valid using given sha1 hex hash.
 pub fn validate_data(hash: &str, reader: &mut impl Read) -> bool {
     let mut hash_bytes = [0u8; 20];
 
-    let decode_res = hex::decode_to_slice(&hash, &mut hash_bytes);
+    let decode_res = hex::decode_to_slice(hash, &mut hash_bytes);
     if decode_res.is_err() {
         return false;
     }
@@ -24,13 +24,29 @@ pub fn validate_data(hash: &str, reader: &mut impl Read) -> bool {
         Ok(_) => {
             let hash = hasher.finalize().to_vec();
 
-            if hash.eq(&hash_bytes) {
-                true
-            } else {
-                false
-            }
+            hash.eq(&hash_bytes)
         },
 
         Err(_) => false
     }
 }
+
+/// Copy reader data to writer. Returns copied length and sha1 hex hash of the data.
+pub fn copy_hashed(reader: &mut impl Read, writer: &mut impl Write) -> io::Result<(u64, String)> {
+    let mut hasher = Sha1::new();
+    let mut len = 0;
+
+    let mut buf = [0u8; 8192];
+    loop {
+        let read = reader.read(&mut buf)?;
+        if read == 0 {
+            break;
+        }
+
+        hasher.update(&buf[..read]);
+        writer.write_all(&buf[..read])?;
+        len += read as u64;
+    }
+
+    Ok((len, hex::encode(hasher.finalize())))
+}
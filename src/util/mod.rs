@@ -4,5 +4,7 @@
  * Copyright (c) storycraft. Licensed under the GNU Lesser General Public License v3.
  */
 
+pub mod bandwidth;
 pub mod hash;
 pub mod file;
+pub mod path;
@@ -0,0 +1,132 @@
+/*
+ * Created on Thu May 20 2021
+ *
+ * Copyright (c) storycraft. Licensed under the GNU General Public License v3.
+ */
+
+use std::path::{Path, PathBuf};
+
+/// Normalize untrusted relative path. Both `/` and `\` are treated as separator on every platform.
+/// Returns None if the path is absolute, has drive or UNC prefix or contains parent directory component,
+/// so it cannot escape its root.
+pub fn normalize_relative(path: &Path) -> Option<PathBuf> {
+    let path = path.to_string_lossy();
+
+    if path.starts_with(is_separator) {
+        return None;
+    }
+
+    let mut normalized = PathBuf::new();
+
+    for name in path.split(is_separator) {
+        match name {
+            "" | "." => {}
+
+            ".." => return None,
+
+            // Drive prefix
+            _ if name.contains(':') => return None,
+
+            _ => normalized.push(name),
+        }
+    }
+
+    Some(normalized)
+}
+
+/// Join untrusted relative path onto root.
+/// Returns None if the joined path would be outside of root.
+pub fn join_relative(root: &Path, path: &Path) -> Option<PathBuf> {
+    normalize_relative(path).map(|path| root.join(path))
+}
+
+/// Relative install path of file from untrusted directory path and file name.
+/// Returns None if the path is not relative or name is not a plain file name.
+pub fn join_install_path(path: &str, name: &str) -> Option<PathBuf> {
+    let dir = normalize_relative(Path::new(path))?;
+    let name = normalize_relative(Path::new(name))?;
+
+    if name.components().count() != 1 {
+        return None;
+    }
+
+    Some(dir.join(name))
+}
+
+/// true if ch is path separator of any platform
+fn is_separator(ch: char) -> bool {
+    ch == '/' || ch == '\\'
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::{join_install_path, join_relative, normalize_relative};
+
+    #[test]
+    fn normalize_plain_path() {
+        assert_eq!(normalize_relative(Path::new("mods/a.jar")), Some(Path::new("mods").join("a.jar")));
+        assert_eq!(normalize_relative(Path::new("./config/./a.cfg")), Some(Path::new("config").join("a.cfg")));
+        assert_eq!(normalize_relative(Path::new("")), Some(Path::new("").to_path_buf()));
+    }
+
+    #[test]
+    fn normalize_empty_segments() {
+        assert_eq!(normalize_relative(Path::new("mods//a.jar/")), Some(Path::new("mods").join("a.jar")));
+    }
+
+    #[test]
+    fn normalize_backslash_separator() {
+        assert_eq!(normalize_relative(Path::new("config\\sub\\a.cfg")), Some(Path::new("config").join("sub").join("a.cfg")));
+        assert_eq!(normalize_relative(Path::new("config\\..\\..\\a.cfg")), None);
+    }
+
+    #[test]
+    fn reject_parent_dir() {
+        assert_eq!(normalize_relative(Path::new("..")), None);
+        assert_eq!(normalize_relative(Path::new("../a.jar")), None);
+        assert_eq!(normalize_relative(Path::new("mods/../../a.jar")), None);
+        assert_eq!(normalize_relative(Path::new("mods/..")), None);
+    }
+
+    #[test]
+    fn reject_absolute() {
+        assert_eq!(normalize_relative(Path::new("/etc/passwd")), None);
+        assert_eq!(normalize_relative(Path::new("\\Windows\\a.dll")), None);
+    }
+
+    #[test]
+    fn reject_prefix() {
+        assert_eq!(normalize_relative(Path::new("C:\\Windows\\a.dll")), None);
+        assert_eq!(normalize_relative(Path::new("C:a.dll")), None);
+        assert_eq!(normalize_relative(Path::new("C:/Windows/a.dll")), None);
+        assert_eq!(normalize_relative(Path::new("\\\\server\\share\\a.dll")), None);
+        assert_eq!(normalize_relative(Path::new("//server/share/a.dll")), None);
+        assert_eq!(normalize_relative(Path::new("\\\\?\\C:\\a.dll")), None);
+    }
+
+    #[test]
+    fn join_stays_under_root() {
+        let root = Path::new("/tmp/pack");
+
+        for path in &["mods/a.jar", "a//b\\c", "./a", "", "mods/"] {
+            let joined = join_relative(root, Path::new(path)).unwrap();
+
+            assert!(joined.starts_with(root), "{} escaped to {}", path, joined.display());
+        }
+
+        for path in &["../a", "a/../../b", "/a", "\\a", "C:\\a", "\\\\server\\share", "a\\..\\..\\b"] {
+            assert_eq!(join_relative(root, Path::new(path)), None, "{} was accepted", path);
+        }
+    }
+
+    #[test]
+    fn install_path_with_plain_name() {
+        assert_eq!(join_install_path("mods", "a.jar"), Some(Path::new("mods").join("a.jar")));
+        assert_eq!(join_install_path("", "a.jar"), Some(Path::new("a.jar").to_path_buf()));
+        assert_eq!(join_install_path("mods", "sub/a.jar"), None);
+        assert_eq!(join_install_path("mods", ""), None);
+        assert_eq!(join_install_path("../mods", "a.jar"), None);
+    }
+}